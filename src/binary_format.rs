@@ -0,0 +1,257 @@
+//! A compact binary format for changesets.
+//!
+//! The format starts with a header consisting of the magic number `MAGIC` followed by a single
+//! byte containing the format version. After that follow length-prefixed records, each encoded
+//! using bincode. The length is stored as a little endian `u64`. The first record contains the
+//! earliest timestamp of the changeset together with its annotations encoded as a JSON string
+//! and its risk scores. All following records contain a single entry as a `(path, diff)` pair.
+//!
+//! Unknown metadata changes are stored with their value encoded as a JSON string.
+//!
+//! In version 1 of the format the first record contained only the earliest timestamp. Version 2
+//! did not support unknown metadata changes, so files containing them cannot be read. Versions
+//! 1 to 3 did not store risk scores, so they are read without any.
+//!
+//! Since the entries are stored one after another, the format can be read and written in a
+//! streaming fashion and new entries can be appended to an existing file.
+
+use std::io::{self, Read, Write};
+
+use crate::{Changeset, MetaEntryDiff};
+
+/// The magic number at the start of the format.
+pub const MAGIC: &[u8; 8] = b"SNIFFCS\0";
+
+/// The current version of the format.
+pub const VERSION: u8 = 4;
+
+/// Converts a bincode error into an I/O error.
+fn to_io_error(err: bincode::ErrorKind) -> io::Error {
+    match err {
+        bincode::ErrorKind::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// Writes a single length-prefixed record.
+fn write_record<W: Write, T: serde::Serialize>(w: &mut W, value: &T) -> io::Result<()> {
+    let bytes = bincode::serialize(value).map_err(|err| to_io_error(*err))?;
+    w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    w.write_all(&bytes)
+}
+
+/// Reads a single length-prefixed record.
+///
+/// If the reader is at its end before the record starts, `None` is returned.
+fn read_record<R: Read, T: serde::de::DeserializeOwned>(r: &mut R) -> io::Result<Option<T>> {
+    let mut len = [0; 8];
+    let mut filled = 0;
+    while filled < len.len() {
+        match r.read(&mut len[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let len = usize::try_from(u64::from_le_bytes(len))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let mut bytes = Vec::new();
+    r.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    bincode::deserialize(&bytes)
+        .map(Some)
+        .map_err(|err| to_io_error(*err))
+}
+
+/// Writes the changeset in the binary format.
+pub fn write<W: Write, Timestamp: serde::Serialize>(
+    cs: &Changeset<Timestamp>,
+    mut w: W,
+) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])?;
+    let annotations = serde_json::to_string(&cs.annotations)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    write_record(
+        &mut w,
+        &(&cs.earliest_timestamp, annotations, &cs.risk_scores),
+    )?;
+    for entry in &cs.changes {
+        write_record(&mut w, &entry)?;
+    }
+
+    w.flush()
+}
+
+/// Reads a changeset in the binary format.
+///
+/// An error is returned if the magic number does not match or the version is not supported.
+pub fn read<R: Read, Timestamp: serde::de::DeserializeOwned>(
+    mut r: R,
+) -> io::Result<Changeset<Timestamp>> {
+    let mut header = [0; MAGIC.len() + 1];
+    r.read_exact(&mut header)?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a changeset in the binary format",
+        ));
+    }
+    let parse_annotations = |annotations: String| {
+        serde_json::from_str(&annotations)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    };
+    let (earliest_timestamp, annotations, risk_scores) = match header[MAGIC.len()] {
+        1 => (
+            read_record(&mut r)?.ok_or(io::ErrorKind::UnexpectedEof)?,
+            Default::default(),
+            Default::default(),
+        ),
+        2 | 3 => {
+            let (earliest_timestamp, annotations): (_, String) =
+                read_record(&mut r)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+            (
+                earliest_timestamp,
+                parse_annotations(annotations)?,
+                Default::default(),
+            )
+        }
+        4 => {
+            let (earliest_timestamp, annotations, risk_scores): (_, String, _) =
+                read_record(&mut r)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+            (
+                earliest_timestamp,
+                parse_annotations(annotations)?,
+                risk_scores,
+            )
+        }
+        version => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported binary format version {version}"),
+            ))
+        }
+    };
+    let mut changes = std::collections::BTreeMap::new();
+    while let Some((path, diff)) = read_record::<_, (String, MetaEntryDiff<Timestamp>)>(&mut r)? {
+        changes.insert(path, diff);
+    }
+
+    Ok(Changeset {
+        schema_version: Changeset::SCHEMA_VERSION,
+        earliest_timestamp,
+        changes,
+        annotations,
+        risk_scores,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        tests::{changeset, file_changed, info, modified_at, ts},
+        Change, MetaEntryDiff, MetadataChange, NamedStreamType, UnknownMetadataChange,
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut cs = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::EntryChange(
+                    file_changed(1, 2),
+                    info(vec![
+                        MetadataChange::size(1, 2),
+                        MetadataChange::NamedStream(
+                            NamedStreamType::AlternateDataStream {
+                                name: "Zone.Identifier".to_string(),
+                            },
+                            Change {
+                                from: None,
+                                to: Some(vec![1, 2, 3].into()),
+                            },
+                        ),
+                    ]),
+                ),
+            ),
+            ("/b", MetaEntryDiff::Deleted(modified_at(5))),
+            ("/c", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        cs.earliest_timestamp = ts(3);
+        cs.annotate("/a", serde_json::json!({"verdict": "bad"}));
+        cs.score_risks(crate::default_risk_score);
+        assert!(!cs.risk_scores.is_empty());
+
+        let mut bytes = Vec::new();
+        super::write(&cs, &mut bytes).unwrap();
+        assert_eq!(&bytes[..super::MAGIC.len()], super::MAGIC);
+        assert_eq!(super::read::<_, crate::Timestamp>(&bytes[..]).unwrap(), cs);
+    }
+
+    #[test]
+    fn unknown_changes_round_trip() {
+        let json = r#"{"earliest_timestamp":"1970-01-01 00:00:00.0","changes":{"/a":{"MetaOnlyChange":{"changes":[{"Foo":{"x":1}},{"Size":{"from":1,"to":2}}],"inode":{"Same":null},"created":{"Same":null},"modified":{"Same":null},"accessed":{"Same":null},"inode_modified":{"Same":null}}}}}"#;
+        let cs = crate::Changeset::from_json(json).unwrap();
+        assert_eq!(
+            cs.changes["/a"].meta_info().changes[0],
+            MetadataChange::Unknown(UnknownMetadataChange {
+                tag: "Foo".to_string(),
+                value: serde_json::json!({"x": 1}),
+            })
+        );
+
+        let mut bytes = Vec::new();
+        super::write(&cs, &mut bytes).unwrap();
+        assert_eq!(super::read::<_, crate::Timestamp>(&bytes[..]).unwrap(), cs);
+    }
+
+    #[test]
+    fn bad_magic_number_is_rejected() {
+        let mut bytes = Vec::new();
+        super::write(&changeset(vec![]), &mut bytes).unwrap();
+        bytes[0] = b'X';
+        let err = super::read::<_, crate::Timestamp>(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        let mut bytes = Vec::new();
+        super::write(&changeset(vec![]), &mut bytes).unwrap();
+        bytes[super::MAGIC.len()] = super::VERSION + 1;
+        let err = super::read::<_, crate::Timestamp>(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn version_1_is_read_without_annotations() {
+        let cs = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.push(1);
+        super::write_record(&mut bytes, &cs.earliest_timestamp).unwrap();
+        for entry in &cs.changes {
+            super::write_record(&mut bytes, &entry).unwrap();
+        }
+        assert_eq!(super::read::<_, crate::Timestamp>(&bytes[..]).unwrap(), cs);
+    }
+
+    #[test]
+    fn version_3_is_read_without_risk_scores() {
+        let cs = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.push(3);
+        super::write_record(&mut bytes, &(&cs.earliest_timestamp, "{}")).unwrap();
+        for entry in &cs.changes {
+            super::write_record(&mut bytes, &entry).unwrap();
+        }
+        assert_eq!(super::read::<_, crate::Timestamp>(&bytes[..]).unwrap(), cs);
+    }
+}
@@ -1,6 +1,12 @@
-/// Contains types to transfer data out of sniff.
+//! Contains types to transfer data out of sniff.
 
-use std::fmt;
+use std::{fmt, sync::Arc};
+
+pub mod binary_format;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 /// Represents a change from one value to another.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -19,6 +25,32 @@ impl<T> Change<T> {
             to: f(&self.to),
         }
     }
+
+    /// Maps the contained values to a new value, consuming the change.
+    fn into_map<R, F: FnMut(T) -> R>(self, mut f: F) -> Change<R> {
+        Change {
+            from: f(self.from),
+            to: f(self.to),
+        }
+    }
+}
+
+impl<T: Clone> Change<T> {
+    /// Returns the change going in the opposite direction.
+    fn reversed(&self) -> Change<T> {
+        Change {
+            from: self.to.clone(),
+            to: self.from.clone(),
+        }
+    }
+
+    /// Returns the change from the new value of `self` to the new value of `next`.
+    fn to_new_of(&self, next: &Change<T>) -> Change<T> {
+        Change {
+            from: self.to.clone(),
+            to: next.to.clone(),
+        }
+    }
 }
 
 impl<T: Ord> Change<T> {
@@ -26,6 +58,42 @@ impl<T: Ord> Change<T> {
     pub fn cmp(&self) -> std::cmp::Ordering {
         self.from.cmp(&self.to)
     }
+
+    /// Returns the greater one of the old and the new value.
+    pub fn max(&self) -> &T {
+        std::cmp::max(&self.from, &self.to)
+    }
+
+    /// Returns the lesser one of the old and the new value.
+    pub fn min(&self) -> &T {
+        std::cmp::min(&self.from, &self.to)
+    }
+
+    /// Compares two changes by their old values first and then by their new values.
+    ///
+    /// This is not an `Ord` implementation, since that would shadow `max` and `min`.
+    fn canonical_cmp(&self, other: &Change<T>) -> std::cmp::Ordering {
+        self.from
+            .cmp(&other.from)
+            .then_with(|| self.to.cmp(&other.to))
+    }
+}
+
+impl<T: PartialEq> Change<Option<T>> {
+    /// Returns whether a value was added, going from `None` to `Some`.
+    pub fn is_added(&self) -> bool {
+        self.from.is_none() && self.to.is_some()
+    }
+
+    /// Returns whether a value was removed, going from `Some` to `None`.
+    pub fn is_removed(&self) -> bool {
+        self.from.is_some() && self.to.is_none()
+    }
+
+    /// Returns whether an existing value was changed to a different value.
+    pub fn is_modified(&self) -> bool {
+        matches!((&self.from, &self.to), (Some(from), Some(to)) if from != to)
+    }
 }
 
 /// Represents a possibly changed value.
@@ -38,6 +106,16 @@ pub enum MaybeChange<T> {
 }
 
 impl<T> MaybeChange<T> {
+    /// Returns an unchanged value.
+    pub fn same(value: T) -> MaybeChange<T> {
+        MaybeChange::Same(value)
+    }
+
+    /// Returns a change from `from` to `to`.
+    pub fn changed(from: T, to: T) -> MaybeChange<T> {
+        MaybeChange::Change(Change { from, to })
+    }
+
     /// Maps the contained values to a new value.
     fn map<R, F: FnMut(&T) -> R>(&self, mut f: F) -> MaybeChange<R> {
         match self {
@@ -46,6 +124,14 @@ impl<T> MaybeChange<T> {
         }
     }
 
+    /// Maps the contained values to a new value, consuming the value.
+    fn into_map<R, F: FnMut(T) -> R>(self, mut f: F) -> MaybeChange<R> {
+        match self {
+            MaybeChange::Change(change) => MaybeChange::Change(change.into_map(f)),
+            MaybeChange::Same(val) => MaybeChange::Same(f(val)),
+        }
+    }
+
     /// Returns whether a change occurred or not.
     pub fn is_changed(&self) -> bool {
         matches!(self, Self::Change(_))
@@ -68,10 +154,102 @@ impl<T> MaybeChange<T> {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-#[serde(into = "String", try_from = "&str")]
+impl<T> MaybeChange<Option<T>> {
+    /// Returns a value that was absent and remained absent.
+    pub fn unchanged_none() -> MaybeChange<Option<T>> {
+        MaybeChange::Same(None)
+    }
+}
+
+impl<T> MaybeChange<T> {
+    /// Applies the given function to all contained values.
+    fn for_each_mut<F: FnMut(&mut T)>(&mut self, mut f: F) {
+        match self {
+            MaybeChange::Change(change) => {
+                f(&mut change.from);
+                f(&mut change.to);
+            }
+            MaybeChange::Same(val) => f(val),
+        }
+    }
+}
+
+impl<T: Ord> MaybeChange<T> {
+    /// Compares two possible changes, with changes ordered before unchanged values.
+    fn canonical_cmp(&self, other: &MaybeChange<T>) -> std::cmp::Ordering {
+        match (self, other) {
+            (MaybeChange::Change(a), MaybeChange::Change(b)) => a.canonical_cmp(b),
+            (MaybeChange::Change(_), MaybeChange::Same(_)) => std::cmp::Ordering::Less,
+            (MaybeChange::Same(_), MaybeChange::Change(_)) => std::cmp::Ordering::Greater,
+            (MaybeChange::Same(a), MaybeChange::Same(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> MaybeChange<T> {
+    /// Returns the possible change from the old value of `self` to the new value of `next`.
+    fn then(&self, next: &MaybeChange<T>) -> MaybeChange<T> {
+        if self.old_val() == next.new_val() {
+            MaybeChange::Same(next.new_val().clone())
+        } else {
+            MaybeChange::Change(Change {
+                from: self.old_val().clone(),
+                to: next.new_val().clone(),
+            })
+        }
+    }
+
+    /// Turns a change whose old and new values are the same into an unchanged value.
+    fn collapse_noop(&mut self) {
+        if let MaybeChange::Change(change) = self {
+            if change.from == change.to {
+                *self = MaybeChange::Same(change.to.clone());
+            }
+        }
+    }
+
+    /// Returns the possible change from the new value of `old` to the new value of `new`.
+    fn between(old: &MaybeChange<T>, new: &MaybeChange<T>) -> MaybeChange<T> {
+        if old.new_val() == new.new_val() {
+            MaybeChange::Same(new.new_val().clone())
+        } else {
+            MaybeChange::Change(Change {
+                from: old.new_val().clone(),
+                to: new.new_val().clone(),
+            })
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(into = "String", try_from = "String")]
 pub struct Hash(pub [u8; 32]);
 
+impl Hash {
+    /// The number of bytes in a hash.
+    pub const LEN: usize = 32;
+
+    /// The hash consisting of only zero bytes.
+    ///
+    /// It is used as a marker for unknown or redacted hashes.
+    pub const NULL: Hash = Hash([0; Hash::LEN]);
+
+    /// Returns whether this is the `Hash::NULL` hash.
+    pub fn is_null(&self) -> bool {
+        *self == Hash::NULL
+    }
+
+    /// Returns the bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8; Hash::LEN] {
+        &self.0
+    }
+
+    /// Converts the hash into its bytes.
+    pub fn into_bytes(self) -> [u8; Hash::LEN] {
+        self.0
+    }
+}
+
 impl fmt::Debug for Hash {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for b in &self.0 {
@@ -92,13 +270,127 @@ impl TryFrom<&str> for Hash {
     type Error = String;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut bytes = [0; 32];
+        let mut bytes = [0; Hash::LEN];
         hex::decode_to_slice(value, &mut bytes).map_err(|err| format!("{err}"))?;
 
         Ok(Hash(bytes))
     }
 }
 
+impl TryFrom<String> for Hash {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Hash::try_from(value.as_str())
+    }
+}
+
+impl TryFrom<&[u8]> for Hash {
+    type Error = String;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        <[u8; Hash::LEN]>::try_from(value)
+            .map(Hash)
+            .map_err(|_| format!("expected {} bytes, got {}", Hash::LEN, value.len()))
+    }
+}
+
+impl From<[u8; Hash::LEN]> for Hash {
+    fn from(value: [u8; Hash::LEN]) -> Self {
+        Hash(value)
+    }
+}
+
+/// The algorithms that produce hashes with the length of a `Hash`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum HashAlgorithm {
+    /// SHA-256.
+    Sha256,
+    /// BLAKE3 with 256 bits of output.
+    Blake3,
+    /// SHA3-256.
+    Sha3_256,
+}
+
+impl HashAlgorithm {
+    /// Returns the prefix for the algorithm used in the string representation of a `TypedHash`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Sha3_256 => "sha3-256",
+        }
+    }
+}
+
+/// A hash together with the algorithm that produced it.
+///
+/// It is serialized as the name of the algorithm followed by a colon and the hex encoded
+/// hash, for example `sha256:e3b0...`. When parsing, a hash without a prefix is assumed to be
+/// a SHA-256 hash.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct TypedHash {
+    /// The algorithm that produced the hash.
+    pub algorithm: HashAlgorithm,
+    /// The hash itself.
+    pub digest: Hash,
+}
+
+impl fmt::Debug for TypedHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:?}", self.algorithm.as_str(), self.digest)
+    }
+}
+
+impl From<Hash> for TypedHash {
+    fn from(value: Hash) -> Self {
+        TypedHash {
+            algorithm: HashAlgorithm::Sha256,
+            digest: value,
+        }
+    }
+}
+
+impl From<TypedHash> for String {
+    fn from(value: TypedHash) -> Self {
+        format!("{value:?}")
+    }
+}
+
+impl TryFrom<&str> for TypedHash {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let Some((prefix, digest)) = value.split_once(':') else {
+            return Hash::try_from(value).map(TypedHash::from);
+        };
+        let algorithm = [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Blake3,
+            HashAlgorithm::Sha3_256,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.as_str() == prefix)
+        .ok_or_else(|| format!("unknown hash algorithm `{prefix}`"))?;
+
+        Ok(TypedHash {
+            algorithm,
+            digest: Hash::try_from(digest)?,
+        })
+    }
+}
+
+impl TryFrom<String> for TypedHash {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TypedHash::try_from(value.as_str())
+    }
+}
+
 /// Represents a change of a file system entry.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum EntryDiff {
@@ -120,8 +412,95 @@ pub enum EntryDiff {
     OtherChange,
 }
 
+impl EntryDiff {
+    /// Creates a change of the contents of a file.
+    pub fn file_changed(from: Hash, to: Hash) -> EntryDiff {
+        EntryDiff::FileChanged {
+            hash_change: Change { from, to },
+        }
+    }
+
+    /// Creates a change of the target of a symlink.
+    pub fn symlink_changed(from: String, to: String) -> EntryDiff {
+        EntryDiff::SymlinkChanged {
+            path_change: Change { from, to },
+        }
+    }
+
+    /// Creates a change of the type of an entry.
+    pub fn type_change(from: String, to: String) -> EntryDiff {
+        EntryDiff::TypeChange(Change { from, to })
+    }
+
+    /// Returns the entry diff of `self` followed by `next`.
+    ///
+    /// If the contents of the file or the target of the symlink at the start of `next` are not
+    /// the ones at the end of `self`, `None` is returned. If the entry ends up the same as before
+    /// `self`, `Some(None)` is returned. Type changes take precedence over other changes and
+    /// other combinations result in `EntryDiff::OtherChange`.
+    fn then(&self, next: &EntryDiff) -> Option<Option<EntryDiff>> {
+        let diff = match (self, next) {
+            (
+                EntryDiff::FileChanged { hash_change: a },
+                EntryDiff::FileChanged { hash_change: b },
+            ) => {
+                if a.to != b.from {
+                    return None;
+                }
+                (a.from != b.to).then(|| EntryDiff::FileChanged {
+                    hash_change: a.reversed().to_new_of(b),
+                })
+            }
+            (
+                EntryDiff::SymlinkChanged { path_change: a },
+                EntryDiff::SymlinkChanged { path_change: b },
+            ) => {
+                if a.to != b.from {
+                    return None;
+                }
+                (a.from != b.to).then(|| EntryDiff::SymlinkChanged {
+                    path_change: a.reversed().to_new_of(b),
+                })
+            }
+            (EntryDiff::TypeChange(a), EntryDiff::TypeChange(b)) => {
+                (a.from != b.to).then(|| EntryDiff::TypeChange(a.reversed().to_new_of(b)))
+            }
+            (_, EntryDiff::TypeChange(_)) => Some(next.clone()),
+            (EntryDiff::TypeChange(_), _) => Some(self.clone()),
+            _ => Some(EntryDiff::OtherChange),
+        };
+
+        Some(diff)
+    }
+
+    /// Compares two entry diffs by their variant first and then by their contents.
+    fn canonical_cmp(&self, other: &EntryDiff) -> std::cmp::Ordering {
+        let index = |diff: &EntryDiff| match diff {
+            EntryDiff::FileChanged { .. } => 0,
+            EntryDiff::SymlinkChanged { .. } => 1,
+            EntryDiff::TypeChange(_) => 2,
+            EntryDiff::OtherChange => 3,
+        };
+
+        match (self, other) {
+            (
+                EntryDiff::FileChanged { hash_change: a },
+                EntryDiff::FileChanged { hash_change: b },
+            ) => a.canonical_cmp(b),
+            (
+                EntryDiff::SymlinkChanged { path_change: a },
+                EntryDiff::SymlinkChanged { path_change: b },
+            )
+            | (EntryDiff::TypeChange(a), EntryDiff::TypeChange(b)) => a.canonical_cmp(b),
+            _ => index(self).cmp(&index(other)),
+        }
+    }
+}
+
 /// The types of named streams associated with a path.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
 pub enum NamedStreamType {
     /// The NTFS reparse data of a path.
     ReparseData,
@@ -143,13 +522,33 @@ pub enum NamedStreamType {
 }
 
 /// The format description for timestamps.
+///
+/// The subseconds are always emitted when formatting, but are optional when parsing.
 const TIMESTAMP_FORMAT: &[time::format_description::FormatItem] = time::macros::format_description!(
-    "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:1+]"
+    "[year]-[month]-[day] [hour]:[minute]:[second][optional [.[subsecond digits:1+]]]"
+);
+
+/// The format description for timestamps using the ISO 8601 `T` separator.
+///
+/// This is only used when parsing.
+const TIMESTAMP_FORMAT_ISO: &[time::format_description::FormatItem] = time::macros::format_description!(
+    "[year]-[month]-[day]T[hour]:[minute]:[second][optional [.[subsecond digits:1+]]]"
 );
 
+/// The format description for timestamps without subseconds.
+const TIMESTAMP_FORMAT_SECONDS: &[time::format_description::FormatItem] =
+    time::macros::format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+
 /// Serialization and deserialization of timestamps.
 mod timestamp_serde {
-    /// Serializes a timestamp as a string.
+    /// Converts a timestamp to UTC, failing if it cannot be represented in UTC.
+    pub(super) fn to_utc(timestamp: &time::OffsetDateTime) -> Result<time::OffsetDateTime, String> {
+        timestamp
+            .checked_to_offset(time::UtcOffset::UTC)
+            .ok_or_else(|| format!("{timestamp} cannot be represented in UTC"))
+    }
+
+    /// Serializes a timestamp as a string in UTC.
     pub(super) fn serialize<S>(
         timestamp: &time::OffsetDateTime,
         serializer: S,
@@ -157,13 +556,16 @@ mod timestamp_serde {
     where
         S: serde::Serializer,
     {
-        let as_str = timestamp
+        let as_str = to_utc(timestamp)
+            .map_err(<S::Error as serde::ser::Error>::custom)?
             .format(super::TIMESTAMP_FORMAT)
             .map_err(<S::Error as serde::ser::Error>::custom)?;
         serializer.serialize_str(&as_str)
     }
 
     /// Parses a timestamp from a string in the deserializer.
+    ///
+    /// Both a space and a `T` are accepted between the date and the time.
     pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<time::OffsetDateTime, D::Error>
     where
         D: serde::Deserializer<'de>,
@@ -175,7 +577,8 @@ mod timestamp_serde {
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 formatter.write_str(
-                    "a string representation of a date in `yyyy-mm-dd HH:MM:SS.ssss` format",
+                    "a string representation of a date in `yyyy-mm-dd HH:MM:SS.ssss` format, \
+                     optionally with `T` instead of the space",
                 )
             }
 
@@ -184,6 +587,10 @@ mod timestamp_serde {
                 E: serde::de::Error,
             {
                 time::PrimitiveDateTime::parse(v, super::TIMESTAMP_FORMAT)
+                    .or_else(|err| {
+                        time::PrimitiveDateTime::parse(v, super::TIMESTAMP_FORMAT_ISO)
+                            .map_err(|_| err)
+                    })
                     .map_err(|err| E::custom(err))
                     .map(|time| time.assume_utc())
             }
@@ -194,7 +601,12 @@ mod timestamp_serde {
 }
 
 /// A timestamp.
-#[derive(Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, PartialOrd, Ord)]
+///
+/// The serialized form does not contain an offset, so timestamps are converted to UTC when
+/// serializing. This fails for the few timestamps near the range limits that cannot be
+/// represented in UTC. Comparisons and hashing only consider the instant in time, not the
+/// offset, so serializing and deserializing a timestamp is lossless down to the nanosecond.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 #[serde(transparent)]
 pub struct Timestamp {
     /// The inner timestamp.
@@ -202,6 +614,33 @@ pub struct Timestamp {
     inner: time::OffsetDateTime,
 }
 
+impl PartialEq for Timestamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.unix_timestamp_nanos() == other.unix_timestamp_nanos()
+    }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.unix_timestamp_nanos()
+            .cmp(&other.unix_timestamp_nanos())
+    }
+}
+
+impl std::hash::Hash for Timestamp {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.unix_timestamp_nanos().hash(state);
+    }
+}
+
 impl std::ops::Deref for Timestamp {
     type Target = time::OffsetDateTime;
 
@@ -212,10 +651,148 @@ impl std::ops::Deref for Timestamp {
 
 impl fmt::Debug for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.format(TIMESTAMP_FORMAT).unwrap())
+        match timestamp_serde::to_utc(&self.inner) {
+            Ok(utc) => write!(f, "{}", utc.format(TIMESTAMP_FORMAT).unwrap()),
+            Err(_) => write!(f, "{}", self.inner),
+        }
+    }
+}
+
+impl Timestamp {
+    /// Converts a system time into a timestamp, failing if it is out of the supported range.
+    ///
+    /// Conversions from `SystemTime` using `From` work as well (including times before 1970),
+    /// but panic if the time is out of the supported range.
+    pub fn try_from_system_time(system_time: std::time::SystemTime) -> Result<Self, String> {
+        let inner = match system_time.duration_since(std::time::UNIX_EPOCH) {
+            Ok(after_epoch) => time::Duration::try_from(after_epoch)
+                .ok()
+                .and_then(|after_epoch| time::OffsetDateTime::UNIX_EPOCH.checked_add(after_epoch)),
+            Err(err) => time::Duration::try_from(err.duration())
+                .ok()
+                .and_then(|before_epoch| {
+                    time::OffsetDateTime::UNIX_EPOCH.checked_sub(before_epoch)
+                }),
+        };
+
+        inner
+            .map(|inner| Timestamp { inner })
+            .ok_or_else(|| format!("{system_time:?} is out of the supported range"))
+    }
+
+    /// Converts a date and time into a timestamp in UTC, failing if it cannot be represented in
+    /// UTC, such as `9999-12-31 23:00 -05:00`.
+    ///
+    /// Conversions using `From` keep the offset instead and never fail, but such timestamps
+    /// cannot be serialized.
+    pub fn try_from_offset_date_time(value: time::OffsetDateTime) -> Result<Self, String> {
+        timestamp_serde::to_utc(&value).map(|inner| Timestamp { inner })
+    }
+
+    /// Formats the timestamp with a fixed number of subsecond digits.
+    ///
+    /// Digits beyond the given precision are truncated. With `0` digits, the subseconds are
+    /// omitted entirely. Values above `9` are treated as `9`.
+    ///
+    /// The timestamp is formatted in UTC. If it cannot be represented in UTC, it is formatted
+    /// in its own offset, followed by that offset.
+    pub fn format_with_precision(&self, digits: u8) -> String {
+        match timestamp_serde::to_utc(&self.inner) {
+            Ok(utc) => format_with_precision(&utc, digits),
+            Err(_) => format!(
+                "{} {}",
+                format_with_precision(&self.inner, digits),
+                self.offset()
+            ),
+        }
+    }
+
+    /// Formats the timestamp with exactly nine subsecond digits.
+    ///
+    /// All timestamps with four digit years have the same width, which is useful for aligning
+    /// them in tables. This is only meant for display and is independent of serialization.
+    pub fn format_fixed(&self) -> String {
+        self.format_with_precision(9)
+    }
+
+    /// Returns the timestamp with all components below the given precision set to zero.
+    pub fn truncate_to(&self, precision: TimePrecision) -> Timestamp {
+        let unit = match precision {
+            TimePrecision::Seconds => 1_000_000_000,
+            TimePrecision::Millis => 1_000_000,
+            TimePrecision::Micros => 1_000,
+            TimePrecision::Nanos => 1,
+        };
+        let excess = self.nanosecond() % unit;
+
+        Timestamp {
+            inner: self.inner - time::Duration::nanoseconds(excess.into()),
+        }
+    }
+}
+
+/// Formats the date and time with the given number of subsecond digits, ignoring the offset.
+fn format_with_precision(timestamp: &time::OffsetDateTime, digits: u8) -> String {
+    let digits = usize::from(digits.min(9));
+    let mut formatted = timestamp
+        .format(TIMESTAMP_FORMAT_SECONDS)
+        .expect("timestamps without an offset can always be formatted");
+    if digits > 0 {
+        let subseconds = format!("{:09}", timestamp.nanosecond());
+        formatted.push('.');
+        formatted.push_str(&subseconds[..digits]);
+    }
+
+    formatted
+}
+
+/// A timestamp that is always serialized with `DIGITS` subsecond digits.
+///
+/// It can be used as the timestamp type of a `Changeset` to produce output that is uniformly
+/// formatted, for example `Changeset<FixedPrecisionTimestamp<3>>`. Deserialization accepts any
+/// number of subsecond digits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FixedPrecisionTimestamp<const DIGITS: u8>(pub Timestamp);
+
+impl<const DIGITS: u8> serde::Serialize for FixedPrecisionTimestamp<DIGITS> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let utc = timestamp_serde::to_utc(&self.0.inner)
+            .map_err(<S::Error as serde::ser::Error>::custom)?;
+        serializer.serialize_str(&format_with_precision(&utc, DIGITS))
+    }
+}
+
+impl<'de, const DIGITS: u8> serde::Deserialize<'de> for FixedPrecisionTimestamp<DIGITS> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Timestamp::deserialize(deserializer).map(FixedPrecisionTimestamp)
     }
 }
 
+impl<const DIGITS: u8> From<FixedPrecisionTimestamp<DIGITS>> for Timestamp {
+    fn from(value: FixedPrecisionTimestamp<DIGITS>) -> Self {
+        value.0
+    }
+}
+
+/// The precision of a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum TimePrecision {
+    /// Whole seconds.
+    Seconds,
+    /// Milliseconds.
+    Millis,
+    /// Microseconds.
+    Micros,
+    /// Nanoseconds.
+    Nanos,
+}
+
 impl<T: Into<time::OffsetDateTime>> From<T> for Timestamp {
     fn from(value: T) -> Self {
         Timestamp {
@@ -225,7 +802,10 @@ impl<T: Into<time::OffsetDateTime>> From<T> for Timestamp {
 }
 
 /// Represents a single change in the metadata.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+///
+/// When deserializing from a human readable format, unknown variants (for example those added
+/// by newer versions of sniff) are preserved as `MetadataChange::Unknown` instead of failing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum MetadataChange {
     /// The size changed.
     Size(Change<u64>),
@@ -240,109 +820,6346 @@ pub enum MetadataChange {
     /// The group id changed.
     Gid(Change<Option<u32>>),
     /// A named stream associated with the path changed.
-    NamedStream(NamedStreamType, Change<Option<Vec<u8>>>),
+    ///
+    /// The stream data is reference counted, so that identical data can be shared between
+    /// entries (see `Changeset::intern_streams`). It is serialized the same way as a `Vec<u8>`.
+    NamedStream(NamedStreamType, Change<Option<Arc<[u8]>>>),
+    /// A change that is not known to this version of the crate.
+    ///
+    /// Helpers that look at specific kinds of changes skip unknown changes.
+    Unknown(UnknownMetadataChange),
 }
 
-/// The relevant information about the metadata and its changes.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub struct MetadataInfo<Timestamp> {
-    /// The changes in this diff.
-    pub changes: Vec<MetadataChange>,
-    /// The inode associated with the metadata.
-    pub inode: MaybeChange<Option<u64>>,
-    /// The timestamp of creation associated with the metadata.
-    pub created: MaybeChange<Option<Timestamp>>,
-    /// The timestamp of the last modification associated with the metadata.
-    pub modified: MaybeChange<Option<Timestamp>>,
-    /// The timestamp of the last access associated with the metadata.
-    pub accessed: MaybeChange<Option<Timestamp>>,
-    /// The timestamp of the last inode modification associated with the metadata.
-    pub inode_modified: MaybeChange<Option<Timestamp>>,
+/// A metadata change of a kind that is not known to this version of the crate.
+///
+/// It is serialized the same way it was deserialized, as a map with a single entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownMetadataChange {
+    /// The name of the variant of the change.
+    pub tag: String,
+    /// The raw value of the change.
+    pub value: serde_json::Value,
 }
 
-impl<Timestamp> MetadataInfo<Timestamp> {
-    /// Transforms the contained timestamps by applying the given function to it.
-    fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
-        &self,
-        mut f: F,
-    ) -> MetadataInfo<NewTimestamp> {
-        MetadataInfo {
-            changes: self.changes.clone(),
-            inode: self.inode.clone(),
-            created: self.created.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
-            modified: self.modified.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
-            accessed: self.accessed.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
-            inode_modified: self
-                .inode_modified
-                .map(|ts_opt| ts_opt.as_ref().map(&mut f)),
-        }
+impl serde::Serialize for UnknownMetadataChange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap as _;
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.tag, &self.value)?;
+        map.end()
     }
 }
 
-/// Represents a change of a file system entry and its associated metadata.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub enum MetaEntryDiff<Timestamp> {
-    /// The entry was added.
-    Added(MetadataInfo<Timestamp>),
-    /// The entry was deleted.
-    Deleted(MetadataInfo<Timestamp>),
-    /// Only the metadata changed.
-    MetaOnlyChange(MetadataInfo<Timestamp>),
-    /// The entry changed (and with it likely the metadata too).
-    EntryChange(EntryDiff, MetadataInfo<Timestamp>),
-}
+/// Serialization and deserialization of metadata changes that preserves unknown variants.
+mod metadata_change_serde {
+    use super::{Arc, Change, MetadataChange, NamedStreamType, UnknownMetadataChange};
 
-impl<Timestamp> MetaEntryDiff<Timestamp> {
-    /// Returns the enclosed metadata info.
-    pub fn meta_info(&self) -> &MetadataInfo<Timestamp> {
+    /// The representation of `MetadataChange` in non human readable formats.
+    ///
+    /// Since those formats are usually not self describing, the value of an unknown change is
+    /// stored as a JSON string.
+    #[derive(serde::Deserialize)]
+    #[serde(rename = "MetadataChange")]
+    enum CompactMetadataChange {
+        Size(Change<u64>),
+        NtfsAttributes(Change<Option<u32>>),
+        UnixPermissions(Change<Option<u32>>),
+        Nlink(Change<Option<u64>>),
+        Uid(Change<Option<u32>>),
+        Gid(Change<Option<u32>>),
+        NamedStream(NamedStreamType, Change<Option<Arc<[u8]>>>),
+        Unknown(String, String),
+    }
+
+    impl TryFrom<CompactMetadataChange> for MetadataChange {
+        type Error = serde_json::Error;
+
+        fn try_from(value: CompactMetadataChange) -> Result<Self, Self::Error> {
+            Ok(match value {
+                CompactMetadataChange::Size(change) => MetadataChange::Size(change),
+                CompactMetadataChange::NtfsAttributes(change) => {
+                    MetadataChange::NtfsAttributes(change)
+                }
+                CompactMetadataChange::UnixPermissions(change) => {
+                    MetadataChange::UnixPermissions(change)
+                }
+                CompactMetadataChange::Nlink(change) => MetadataChange::Nlink(change),
+                CompactMetadataChange::Uid(change) => MetadataChange::Uid(change),
+                CompactMetadataChange::Gid(change) => MetadataChange::Gid(change),
+                CompactMetadataChange::NamedStream(ty, change) => {
+                    MetadataChange::NamedStream(ty, change)
+                }
+                CompactMetadataChange::Unknown(tag, value) => {
+                    MetadataChange::Unknown(UnknownMetadataChange {
+                        tag,
+                        value: serde_json::from_str(&value)?,
+                    })
+                }
+            })
+        }
+    }
+
+    impl serde::Serialize for MetadataChange {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            use serde::ser::{Error as _, SerializeTupleVariant as _};
+
+            const NAME: &str = "MetadataChange";
+
+            match self {
+                MetadataChange::Size(change) => {
+                    serializer.serialize_newtype_variant(NAME, 0, "Size", change)
+                }
+                MetadataChange::NtfsAttributes(change) => {
+                    serializer.serialize_newtype_variant(NAME, 1, "NtfsAttributes", change)
+                }
+                MetadataChange::UnixPermissions(change) => {
+                    serializer.serialize_newtype_variant(NAME, 2, "UnixPermissions", change)
+                }
+                MetadataChange::Nlink(change) => {
+                    serializer.serialize_newtype_variant(NAME, 3, "Nlink", change)
+                }
+                MetadataChange::Uid(change) => {
+                    serializer.serialize_newtype_variant(NAME, 4, "Uid", change)
+                }
+                MetadataChange::Gid(change) => {
+                    serializer.serialize_newtype_variant(NAME, 5, "Gid", change)
+                }
+                MetadataChange::NamedStream(ty, change) => {
+                    let mut variant =
+                        serializer.serialize_tuple_variant(NAME, 6, "NamedStream", 2)?;
+                    variant.serialize_field(ty)?;
+                    variant.serialize_field(change)?;
+                    variant.end()
+                }
+                MetadataChange::Unknown(change) if serializer.is_human_readable() => {
+                    change.serialize(serializer)
+                }
+                MetadataChange::Unknown(change) => {
+                    let value = serde_json::to_string(&change.value).map_err(S::Error::custom)?;
+                    let mut variant = serializer.serialize_tuple_variant(NAME, 7, "Unknown", 2)?;
+                    variant.serialize_field(&change.tag)?;
+                    variant.serialize_field(&value)?;
+                    variant.end()
+                }
+            }
+        }
+    }
+
+    /// Visits the map with a single entry that a metadata change is represented as.
+    struct MetadataChangeVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for MetadataChangeVisitor {
+        type Value = MetadataChange;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a map with a single entry")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::MapAccess<'de>,
+        {
+            use serde::de::Error as _;
+
+            let Some(tag) = map.next_key::<String>()? else {
+                return Err(A::Error::invalid_length(0, &self));
+            };
+            // Only unknown changes are buffered, so that known changes with large named
+            // streams are deserialized directly.
+            let change = match tag.as_str() {
+                "Size" => MetadataChange::Size(map.next_value()?),
+                "NtfsAttributes" => MetadataChange::NtfsAttributes(map.next_value()?),
+                "UnixPermissions" => MetadataChange::UnixPermissions(map.next_value()?),
+                "Nlink" => MetadataChange::Nlink(map.next_value()?),
+                "Uid" => MetadataChange::Uid(map.next_value()?),
+                "Gid" => MetadataChange::Gid(map.next_value()?),
+                "NamedStream" => {
+                    let (ty, change) = map.next_value()?;
+                    MetadataChange::NamedStream(ty, change)
+                }
+                _ => MetadataChange::Unknown(UnknownMetadataChange {
+                    tag,
+                    value: map.next_value()?,
+                }),
+            };
+            if map.next_key::<serde::de::IgnoredAny>()?.is_some() {
+                return Err(A::Error::invalid_length(2, &self));
+            }
+
+            Ok(change)
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for MetadataChange {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            if !deserializer.is_human_readable() {
+                return CompactMetadataChange::deserialize(deserializer)?
+                    .try_into()
+                    .map_err(<D::Error as serde::de::Error>::custom);
+            }
+
+            deserializer.deserialize_map(MetadataChangeVisitor)
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// The attribute flags of an entry on NTFS.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct NtfsAttributes: u32 {
+        /// The entry is read only.
+        const READONLY = 0x1;
+        /// The entry is hidden.
+        const HIDDEN = 0x2;
+        /// The entry is used by the operating system.
+        const SYSTEM = 0x4;
+        /// The entry is a directory.
+        const DIRECTORY = 0x10;
+        /// The entry is marked for backup.
+        const ARCHIVE = 0x20;
+        /// The entry is a device.
+        const DEVICE = 0x40;
+        /// The entry has no other attributes set.
+        const NORMAL = 0x80;
+        /// The entry is used for temporary storage.
+        const TEMPORARY = 0x100;
+        /// The entry is a sparse file.
+        const SPARSE_FILE = 0x200;
+        /// The entry has an associated reparse point.
+        const REPARSE_POINT = 0x400;
+        /// The entry is compressed.
+        const COMPRESSED = 0x800;
+        /// The data of the entry is not available immediately.
+        const OFFLINE = 0x1000;
+        /// The entry is not indexed by the content indexing service.
+        const NOT_CONTENT_INDEXED = 0x2000;
+        /// The entry is encrypted.
+        const ENCRYPTED = 0x4000;
+    }
+}
+
+bitflags::bitflags! {
+    /// The permission bits of a Unix file mode.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct UnixMode: u32 {
+        /// The entry is executed with the permissions of its owning user.
+        const SETUID = 0o4000;
+        /// The entry is executed with the permissions of its owning group.
+        const SETGID = 0o2000;
+        /// Only owners may delete or rename entries in the directory.
+        const STICKY = 0o1000;
+        /// The owning user may read the entry.
+        const USER_READ = 0o400;
+        /// The owning user may write the entry.
+        const USER_WRITE = 0o200;
+        /// The owning user may execute the entry.
+        const USER_EXECUTE = 0o100;
+        /// The owning group may read the entry.
+        const GROUP_READ = 0o40;
+        /// The owning group may write the entry.
+        const GROUP_WRITE = 0o20;
+        /// The owning group may execute the entry.
+        const GROUP_EXECUTE = 0o10;
+        /// Everyone else may read the entry.
+        const OTHER_READ = 0o4;
+        /// Everyone else may write the entry.
+        const OTHER_WRITE = 0o2;
+        /// Everyone else may execute the entry.
+        const OTHER_EXECUTE = 0o1;
+    }
+}
+
+/// The permission bits that were changed by a change of the Unix permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnixModeDiff {
+    /// The permission bits that were set.
+    pub set: UnixMode,
+    /// The permission bits that were cleared.
+    pub cleared: UnixMode,
+}
+
+impl UnixModeDiff {
+    /// Returns whether the setuid or the setgid bit was set.
+    ///
+    /// Such changes allow executing the entry with elevated privileges.
+    pub fn sets_privileged_bits(&self) -> bool {
+        self.set.intersects(UnixMode::SETUID | UnixMode::SETGID)
+    }
+}
+
+impl MetadataChange {
+    /// Creates a change of the size.
+    pub fn size(from: u64, to: u64) -> MetadataChange {
+        MetadataChange::Size(Change { from, to })
+    }
+
+    /// Creates a change of the NTFS attributes.
+    pub fn ntfs_attributes(from: Option<u32>, to: Option<u32>) -> MetadataChange {
+        MetadataChange::NtfsAttributes(Change { from, to })
+    }
+
+    /// Creates a change of the unix permissions.
+    pub fn unix_permissions(from: Option<u32>, to: Option<u32>) -> MetadataChange {
+        MetadataChange::UnixPermissions(Change { from, to })
+    }
+
+    /// Creates a change of the number of links.
+    pub fn nlink(from: Option<u64>, to: Option<u64>) -> MetadataChange {
+        MetadataChange::Nlink(Change { from, to })
+    }
+
+    /// Creates a change of the user id.
+    pub fn uid(from: Option<u32>, to: Option<u32>) -> MetadataChange {
+        MetadataChange::Uid(Change { from, to })
+    }
+
+    /// Creates a change of the group id.
+    pub fn gid(from: Option<u32>, to: Option<u32>) -> MetadataChange {
+        MetadataChange::Gid(Change { from, to })
+    }
+
+    /// Returns the category of the change.
+    ///
+    /// Changes of the NTFS access control list count as permission changes, while all other
+    /// named streams are in `MetadataChangeCategory::NamedStreams`.
+    pub fn category(&self) -> MetadataChangeCategory {
         match self {
-            MetaEntryDiff::Added(info)
-            | MetaEntryDiff::Deleted(info)
-            | MetaEntryDiff::EntryChange(_, info)
-            | MetaEntryDiff::MetaOnlyChange(info) => info,
+            MetadataChange::Size(_) => MetadataChangeCategory::Size,
+            MetadataChange::NtfsAttributes(_)
+            | MetadataChange::UnixPermissions(_)
+            | MetadataChange::NamedStream(NamedStreamType::AccessControlList, _) => {
+                MetadataChangeCategory::Permissions
+            }
+            MetadataChange::Nlink(_) => MetadataChangeCategory::Links,
+            MetadataChange::Uid(_) | MetadataChange::Gid(_) => MetadataChangeCategory::Ownership,
+            MetadataChange::NamedStream(_, _) => MetadataChangeCategory::NamedStreams,
+            MetadataChange::Unknown(_) => MetadataChangeCategory::Unknown,
         }
     }
 
-    /// Transforms the contained timestamps by applying the given function to it.
-    pub fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
-        &self,
-        f: F,
-    ) -> MetaEntryDiff<NewTimestamp> {
+    /// Returns the signed difference of a size change.
+    ///
+    /// If this is not a size change, `None` is returned.
+    pub fn size_delta(&self) -> Option<i128> {
         match self {
-            MetaEntryDiff::Added(meta) => MetaEntryDiff::Added(meta.transform_timestamps(f)),
-            MetaEntryDiff::Deleted(meta) => MetaEntryDiff::Deleted(meta.transform_timestamps(f)),
-            MetaEntryDiff::MetaOnlyChange(meta) => {
-                MetaEntryDiff::MetaOnlyChange(meta.transform_timestamps(f))
+            MetadataChange::Size(change) => Some(i128::from(change.to) - i128::from(change.from)),
+            _ => None,
+        }
+    }
+
+    /// Returns a human readable description of a size change.
+    ///
+    /// The sizes are formatted using binary units, followed by the signed difference, for
+    /// example `4.00 TiB -> 4.00 TiB (+4.00 GiB)`. If this is not a size change, `None` is
+    /// returned.
+    pub fn describe_size(&self) -> Option<String> {
+        let MetadataChange::Size(change) = self else {
+            return None;
+        };
+
+        let sign = if change.to < change.from { '-' } else { '+' };
+        Some(format!(
+            "{} -> {} ({sign}{})",
+            format_size(change.from),
+            format_size(change.to),
+            format_size(change.from.abs_diff(change.to)),
+        ))
+    }
+
+    /// Returns the NTFS attribute flags that were added and removed by this change.
+    ///
+    /// Missing attributes are treated as having no flags set. Unknown bits are retained. If
+    /// this is not a change of the NTFS attributes, `None` is returned.
+    pub fn ntfs_attribute_diff(&self) -> Option<(NtfsAttributes, NtfsAttributes)> {
+        let MetadataChange::NtfsAttributes(change) = self else {
+            return None;
+        };
+
+        let from = NtfsAttributes::from_bits_retain(change.from.unwrap_or(0));
+        let to = NtfsAttributes::from_bits_retain(change.to.unwrap_or(0));
+        Some((to.difference(from), from.difference(to)))
+    }
+
+    /// Returns the permission bits that were set and cleared by this change.
+    ///
+    /// Missing permissions are treated as having no bits set. Bits other than the permission
+    /// bits, such as the file type, are ignored. If this is not a change of the Unix
+    /// permissions, `None` is returned.
+    pub fn unix_mode_diff(&self) -> Option<UnixModeDiff> {
+        let MetadataChange::UnixPermissions(change) = self else {
+            return None;
+        };
+
+        let from = UnixMode::from_bits_truncate(change.from.unwrap_or(0));
+        let to = UnixMode::from_bits_truncate(change.to.unwrap_or(0));
+        Some(UnixModeDiff {
+            set: to.difference(from),
+            cleared: from.difference(to),
+        })
+    }
+
+    /// Returns whether the change grants access that was not granted before.
+    ///
+    /// For Unix permissions this is the case if a read, write or execute bit or the setuid or
+    /// setgid bit is set, or if the sticky bit is cleared. For NTFS attributes this is the case
+    /// if `NtfsAttributes::READONLY` is removed. A change that grants some access while also
+    /// revoking other access counts as loosening. All other changes return `false`.
+    pub fn is_permission_loosening(&self) -> bool {
+        if let Some(diff) = self.unix_mode_diff() {
+            return !diff.set.difference(UnixMode::STICKY).is_empty()
+                || diff.cleared.contains(UnixMode::STICKY);
+        }
+        if let Some((_, removed)) = self.ntfs_attribute_diff() {
+            return removed.contains(NtfsAttributes::READONLY);
+        }
+
+        false
+    }
+
+    /// Returns a human readable description of the change.
+    ///
+    /// Sizes are described as in `describe_size`, permissions are written in octal and NTFS
+    /// attributes in hexadecimal. Named streams are described by the size of their data.
+    pub fn describe(&self) -> String {
+        match self {
+            MetadataChange::Size(_) => format!("size: {}", self.describe_size().unwrap()),
+            MetadataChange::NtfsAttributes(change) => format!(
+                "NTFS attributes: {}",
+                describe_optional_change(
+                    &change.map(|attrs| attrs.map(|attrs| format!("{attrs:#x}")))
+                )
+            ),
+            MetadataChange::UnixPermissions(change) => format!(
+                "permissions: {}",
+                describe_optional_change(&change.map(|mode| mode.map(|mode| format!("{mode:o}"))))
+            ),
+            MetadataChange::Nlink(change) => format!("links: {}", describe_optional_change(change)),
+            MetadataChange::Uid(change) => format!("uid: {}", describe_optional_change(change)),
+            MetadataChange::Gid(change) => format!("gid: {}", describe_optional_change(change)),
+            MetadataChange::NamedStream(ty, change) => {
+                let name = match ty {
+                    NamedStreamType::ReparseData => "reparse data".to_string(),
+                    NamedStreamType::AccessControlList => "access control list".to_string(),
+                    NamedStreamType::DosName => "DOS name".to_string(),
+                    NamedStreamType::ObjectId => "object ID".to_string(),
+                    NamedStreamType::EncryptedFileSystemInfo => {
+                        "encrypted file system info".to_string()
+                    }
+                    NamedStreamType::ExtendedAttributes => "extended attributes".to_string(),
+                    NamedStreamType::AlternateDataStream { name } => {
+                        format!("alternate data stream `{name}`")
+                    }
+                };
+                let sizes =
+                    change.map(|data| data.as_ref().map(|data| format_size(data.len() as u64)));
+                format!("{name}: {}", describe_optional_change(&sizes))
             }
-            MetaEntryDiff::EntryChange(entry, meta) => {
-                MetaEntryDiff::EntryChange(entry.clone(), meta.transform_timestamps(f))
+            MetadataChange::Unknown(change) => format!("unknown change `{}`", change.tag),
+        }
+    }
+
+    /// Compares two changes by their variant first and then by their contents.
+    ///
+    /// Unknown changes are compared by their tag and then by their JSON representation.
+    fn canonical_cmp(&self, other: &MetadataChange) -> std::cmp::Ordering {
+        let index = |change: &MetadataChange| match change {
+            MetadataChange::Size(_) => 0,
+            MetadataChange::NtfsAttributes(_) => 1,
+            MetadataChange::UnixPermissions(_) => 2,
+            MetadataChange::Nlink(_) => 3,
+            MetadataChange::Uid(_) => 4,
+            MetadataChange::Gid(_) => 5,
+            MetadataChange::NamedStream(_, _) => 6,
+            MetadataChange::Unknown(_) => 7,
+        };
+
+        match (self, other) {
+            (MetadataChange::Size(a), MetadataChange::Size(b)) => a.canonical_cmp(b),
+            (MetadataChange::Nlink(a), MetadataChange::Nlink(b)) => a.canonical_cmp(b),
+            (MetadataChange::NtfsAttributes(a), MetadataChange::NtfsAttributes(b))
+            | (MetadataChange::UnixPermissions(a), MetadataChange::UnixPermissions(b))
+            | (MetadataChange::Uid(a), MetadataChange::Uid(b))
+            | (MetadataChange::Gid(a), MetadataChange::Gid(b)) => a.canonical_cmp(b),
+            (MetadataChange::NamedStream(ty_a, a), MetadataChange::NamedStream(ty_b, b)) => {
+                ty_a.cmp(ty_b).then_with(|| a.canonical_cmp(b))
+            }
+            (MetadataChange::Unknown(a), MetadataChange::Unknown(b)) => a
+                .tag
+                .cmp(&b.tag)
+                .then_with(|| a.value.to_string().cmp(&b.value.to_string())),
+            _ => index(self).cmp(&index(other)),
+        }
+    }
+
+    /// Returns whether both changes concern the same metadata field.
+    fn same_field(&self, other: &MetadataChange) -> bool {
+        match (self, other) {
+            (MetadataChange::NamedStream(a, _), MetadataChange::NamedStream(b, _)) => a == b,
+            (MetadataChange::Unknown(a), MetadataChange::Unknown(b)) => a.tag == b.tag,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+
+    /// Returns whether the old value and the new value of the change are the same.
+    fn is_noop(&self) -> bool {
+        match self {
+            MetadataChange::Size(change) => change.from == change.to,
+            MetadataChange::NtfsAttributes(change)
+            | MetadataChange::UnixPermissions(change)
+            | MetadataChange::Uid(change)
+            | MetadataChange::Gid(change) => change.from == change.to,
+            MetadataChange::Nlink(change) => change.from == change.to,
+            MetadataChange::NamedStream(_, change) => change.from == change.to,
+            MetadataChange::Unknown(_) => false,
+        }
+    }
+
+    /// Returns the change going in the opposite direction.
+    fn reversed(&self) -> MetadataChange {
+        match self {
+            MetadataChange::Size(change) => MetadataChange::Size(change.reversed()),
+            MetadataChange::NtfsAttributes(change) => {
+                MetadataChange::NtfsAttributes(change.reversed())
+            }
+            MetadataChange::UnixPermissions(change) => {
+                MetadataChange::UnixPermissions(change.reversed())
+            }
+            MetadataChange::Nlink(change) => MetadataChange::Nlink(change.reversed()),
+            MetadataChange::Uid(change) => MetadataChange::Uid(change.reversed()),
+            MetadataChange::Gid(change) => MetadataChange::Gid(change.reversed()),
+            MetadataChange::NamedStream(ty, change) => {
+                MetadataChange::NamedStream(ty.clone(), change.reversed())
             }
+            MetadataChange::Unknown(_) => self.clone(),
         }
     }
+
+    /// Returns the change from the new value of `self` to the new value of `next`.
+    ///
+    /// If both changes concern different fields, `None` is returned.
+    fn to_new_of(&self, next: &MetadataChange) -> Option<MetadataChange> {
+        Some(match (self, next) {
+            (MetadataChange::Size(a), MetadataChange::Size(b)) => {
+                MetadataChange::Size(a.to_new_of(b))
+            }
+            (MetadataChange::NtfsAttributes(a), MetadataChange::NtfsAttributes(b)) => {
+                MetadataChange::NtfsAttributes(a.to_new_of(b))
+            }
+            (MetadataChange::UnixPermissions(a), MetadataChange::UnixPermissions(b)) => {
+                MetadataChange::UnixPermissions(a.to_new_of(b))
+            }
+            (MetadataChange::Nlink(a), MetadataChange::Nlink(b)) => {
+                MetadataChange::Nlink(a.to_new_of(b))
+            }
+            (MetadataChange::Uid(a), MetadataChange::Uid(b)) => MetadataChange::Uid(a.to_new_of(b)),
+            (MetadataChange::Gid(a), MetadataChange::Gid(b)) => MetadataChange::Gid(a.to_new_of(b)),
+            (MetadataChange::NamedStream(ty_a, a), MetadataChange::NamedStream(ty_b, b))
+                if ty_a == ty_b =>
+            {
+                MetadataChange::NamedStream(ty_a.clone(), a.to_new_of(b))
+            }
+            _ => return None,
+        })
+    }
 }
 
-/// Represents a set of changes for a whole diff tree.
+impl From<Change<u64>> for MetadataChange {
+    fn from(value: Change<u64>) -> Self {
+        MetadataChange::Size(value)
+    }
+}
+
+/// Two metadata changes that disagree about the same field.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub struct Changeset<Timestamp> {
-    /// The earliest possible in this changeset.
-    pub earliest_timestamp: self::Timestamp,
-    /// All the changes in this change set.
-    pub changes: std::collections::BTreeMap<String, MetaEntryDiff<Timestamp>>,
+pub struct ChangeConflict {
+    /// The change of the field in the first list of changes.
+    pub first: MetadataChange,
+    /// The change of the field in the second list of changes.
+    pub second: MetadataChange,
 }
 
-impl<Timestamp> Changeset<Timestamp> {
+/// Merges two lists of changes observed for the same path.
+///
+/// Two changes conflict if they concern the same field but are not equal. Named stream changes
+/// concern the same field if their stream types are equal. Changes that occur in both lists are
+/// only included once.
+///
+/// If there are any conflicts, all of them are returned instead.
+pub fn merge_changes(
+    a: &[MetadataChange],
+    b: &[MetadataChange],
+) -> Result<Vec<MetadataChange>, Vec<ChangeConflict>> {
+    let mut merged = a.to_vec();
+    let mut conflicts = Vec::new();
+
+    for second in b {
+        match a.iter().find(|first| first.same_field(second)) {
+            Some(first) if first != second => conflicts.push(ChangeConflict {
+                first: first.clone(),
+                second: second.clone(),
+            }),
+            Some(_) => (),
+            None => merged.push(second.clone()),
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// The kinds of timestamps associated with the metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum TimestampKind {
+    /// The timestamp of creation.
+    Created,
+    /// The timestamp of the last modification.
+    Modified,
+    /// The timestamp of the last access.
+    Accessed,
+    /// The timestamp of the last inode modification.
+    InodeModified,
+}
+
+/// Formats a number of bytes using binary units, such as `1.50 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Values that would be rounded up to `1024.00` are shown in the next unit instead.
+    while value >= 1024.0 - 0.005 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.2} {}", UNITS[unit])
+}
+
+/// Describes a change of an optional value, such as `(unset) -> 755` or `644 -> (removed)`.
+fn describe_optional_change<T: fmt::Display>(change: &Change<Option<T>>) -> String {
+    let from = match &change.from {
+        Some(from) => from.to_string(),
+        None => "(unset)".to_string(),
+    };
+    let to = match (&change.from, &change.to) {
+        (_, Some(to)) => to.to_string(),
+        (Some(_), None) => "(removed)".to_string(),
+        (None, None) => "(unset)".to_string(),
+    };
+
+    format!("{from} -> {to}")
+}
+
+/// The relevant information about the metadata and its changes.
+///
+/// In human readable formats the changes are omitted when there are none.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+pub struct MetadataInfo<Timestamp> {
+    /// The changes in this diff.
+    #[serde(default)]
+    pub changes: Vec<MetadataChange>,
+    /// The inode associated with the metadata.
+    pub inode: MaybeChange<Option<u64>>,
+    /// The timestamp of creation associated with the metadata.
+    pub created: MaybeChange<Option<Timestamp>>,
+    /// The timestamp of the last modification associated with the metadata.
+    pub modified: MaybeChange<Option<Timestamp>>,
+    /// The timestamp of the last access associated with the metadata.
+    pub accessed: MaybeChange<Option<Timestamp>>,
+    /// The timestamp of the last inode modification associated with the metadata.
+    pub inode_modified: MaybeChange<Option<Timestamp>>,
+}
+
+impl<Timestamp: serde::Serialize> serde::Serialize for MetadataInfo<Timestamp> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct as _;
+
+        // Non human readable formats are usually not self describing, so fields cannot be
+        // skipped there.
+        let skip_changes = serializer.is_human_readable() && self.changes.is_empty();
+        let mut info =
+            serializer.serialize_struct("MetadataInfo", if skip_changes { 5 } else { 6 })?;
+        if skip_changes {
+            info.skip_field("changes")?;
+        } else {
+            info.serialize_field("changes", &self.changes)?;
+        }
+        info.serialize_field("inode", &self.inode)?;
+        info.serialize_field("created", &self.created)?;
+        info.serialize_field("modified", &self.modified)?;
+        info.serialize_field("accessed", &self.accessed)?;
+        info.serialize_field("inode_modified", &self.inode_modified)?;
+        info.end()
+    }
+}
+
+impl<Timestamp> MetadataInfo<Timestamp> {
     /// Transforms the contained timestamps by applying the given function to it.
-    pub fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
+    fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
         &self,
         mut f: F,
-    ) -> Changeset<NewTimestamp> {
-        Changeset {
-            earliest_timestamp: self.earliest_timestamp.clone(),
-            changes: self
+    ) -> MetadataInfo<NewTimestamp> {
+        MetadataInfo {
+            changes: self.changes.clone(),
+            inode: self.inode.clone(),
+            created: self.created.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
+            modified: self.modified.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
+            accessed: self.accessed.map(|ts_opt| ts_opt.as_ref().map(&mut f)),
+            inode_modified: self
+                .inode_modified
+                .map(|ts_opt| ts_opt.as_ref().map(&mut f)),
+        }
+    }
+
+    /// Transforms the contained timestamps by applying the given function to it, consuming
+    /// the info.
+    fn into_transform_timestamps<NewTimestamp, F: FnMut(Timestamp) -> NewTimestamp>(
+        self,
+        mut f: F,
+    ) -> MetadataInfo<NewTimestamp> {
+        MetadataInfo {
+            changes: self.changes,
+            inode: self.inode,
+            created: self.created.into_map(|ts_opt| ts_opt.map(&mut f)),
+            modified: self.modified.into_map(|ts_opt| ts_opt.map(&mut f)),
+            accessed: self.accessed.into_map(|ts_opt| ts_opt.map(&mut f)),
+            inode_modified: self.inode_modified.into_map(|ts_opt| ts_opt.map(&mut f)),
+        }
+    }
+
+    /// Returns whether both infos are equal when ignoring the timestamps.
+    fn eq_ignoring_timestamps<OtherTimestamp>(&self, other: &MetadataInfo<OtherTimestamp>) -> bool {
+        self.changes == other.changes && self.inode == other.inode
+    }
+
+    /// Returns the new timestamp that best describes when the entry was last changed.
+    ///
+    /// This is the new modification timestamp, falling back to the new creation timestamp and
+    /// then to the new access timestamp.
+    fn effective_timestamp(&self) -> Option<&Timestamp> {
+        self.modified
+            .new_val()
+            .as_ref()
+            .or(self.created.new_val().as_ref())
+            .or(self.accessed.new_val().as_ref())
+    }
+
+    /// Returns all timestamp fields together with their kinds.
+    fn timestamp_fields(&self) -> [(TimestampKind, &MaybeChange<Option<Timestamp>>); 4] {
+        [
+            (TimestampKind::Created, &self.created),
+            (TimestampKind::Modified, &self.modified),
+            (TimestampKind::Accessed, &self.accessed),
+            (TimestampKind::InodeModified, &self.inode_modified),
+        ]
+    }
+
+    /// Returns the old and the new value of every timestamp field together with its kind.
+    ///
+    /// Unchanged fields have equal old and new values.
+    pub fn timestamp_pairs(&self) -> [(TimestampKind, Option<&Timestamp>, Option<&Timestamp>); 4] {
+        self.timestamp_fields()
+            .map(|(kind, field)| (kind, field.old_val().as_ref(), field.new_val().as_ref()))
+    }
+
+    /// Coalesces multiple changes of the same named stream into a single one.
+    ///
+    /// The changes of a stream are chained in the order in which they appear: the coalesced
+    /// change goes from the old data of the first change to the new data of the last change and
+    /// takes the position of the first change. Named stream changes whose old and new data are
+    /// the same afterwards are removed, since they have no net effect. Streams are the same if
+    /// their types are equal, so alternate data streams are distinguished by their names.
+    pub fn coalesce_named_streams(&mut self) {
+        let mut changes: Vec<MetadataChange> = Vec::with_capacity(self.changes.len());
+        for change in std::mem::take(&mut self.changes) {
+            if let MetadataChange::NamedStream(ty, next) = &change {
+                let existing = changes.iter_mut().find_map(|existing| match existing {
+                    MetadataChange::NamedStream(existing_ty, existing) if existing_ty == ty => {
+                        Some(existing)
+                    }
+                    _ => None,
+                });
+                if let Some(existing) = existing {
+                    existing.to = next.to.clone();
+                    continue;
+                }
+            }
+            changes.push(change);
+        }
+        changes.retain(|change| {
+            !matches!(change, MetadataChange::NamedStream(..)) || !change.is_noop()
+        });
+
+        self.changes = changes;
+    }
+
+    /// Applies the given function to all contained timestamps.
+    fn for_each_timestamp_mut<F: FnMut(&mut Timestamp)>(&mut self, mut f: F) {
+        for field in [
+            &mut self.created,
+            &mut self.modified,
+            &mut self.accessed,
+            &mut self.inode_modified,
+        ] {
+            field.for_each_mut(|ts_opt| {
+                if let Some(ts) = ts_opt {
+                    f(ts)
+                }
+            });
+        }
+    }
+}
+
+impl<Timestamp: Ord> MetadataInfo<Timestamp> {
+    /// Compares two infos by their changes first and then by the remaining fields in order.
+    fn canonical_cmp(&self, other: &MetadataInfo<Timestamp>) -> std::cmp::Ordering {
+        self.changes
+            .iter()
+            .zip(&other.changes)
+            .map(|(a, b)| a.canonical_cmp(b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| self.changes.len().cmp(&other.changes.len()))
+            .then_with(|| self.inode.canonical_cmp(&other.inode))
+            .then_with(|| self.created.canonical_cmp(&other.created))
+            .then_with(|| self.modified.canonical_cmp(&other.modified))
+            .then_with(|| self.accessed.canonical_cmp(&other.accessed))
+            .then_with(|| self.inode_modified.canonical_cmp(&other.inode_modified))
+    }
+
+    /// Returns the kinds of all timestamps that were changed to an earlier point in time.
+    ///
+    /// Timestamps that were added or removed are not considered to be backdated.
+    pub fn backdated_timestamps(&self) -> Vec<TimestampKind> {
+        self.timestamp_fields()
+            .into_iter()
+            .filter_map(|(kind, field)| match field {
+                MaybeChange::Change(Change {
+                    from: Some(from),
+                    to: Some(to),
+                }) if to < from => Some(kind),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl<Timestamp: PartialEq> MetadataInfo<Timestamp> {
+    /// Returns whether both infos are equal when ignoring the order of the changes.
+    fn semantic_eq(&self, other: &MetadataInfo<Timestamp>) -> bool {
+        let count =
+            |changes: &[MetadataChange], change| changes.iter().filter(|c| *c == change).count();
+
+        self.changes.len() == other.changes.len()
+            && self
                 .changes
                 .iter()
-                .map(|(path, diff)| (path.clone(), diff.transform_timestamps(&mut f)))
-                .collect(),
+                .all(|change| count(&self.changes, change) == count(&other.changes, change))
+            && self.inode == other.inode
+            && self.created == other.created
+            && self.modified == other.modified
+            && self.accessed == other.accessed
+            && self.inode_modified == other.inode_modified
+    }
+}
+
+impl<Timestamp: Clone + PartialEq> MetadataInfo<Timestamp> {
+    /// Returns the metadata info of `self` followed by `next`.
+    ///
+    /// The resulting info goes from the old values of `self` to the new values of `next`.
+    fn then(&self, next: &MetadataInfo<Timestamp>) -> MetadataInfo<Timestamp> {
+        let mut changes = Vec::new();
+
+        for change in &self.changes {
+            match next
+                .changes
+                .iter()
+                .find_map(|next_change| change.reversed().to_new_of(next_change))
+            {
+                Some(change) if change.is_noop() => (),
+                Some(change) => changes.push(change),
+                None => changes.push(change.clone()),
+            }
+        }
+        for next_change in &next.changes {
+            if !self
+                .changes
+                .iter()
+                .any(|change| change.to_new_of(next_change).is_some())
+            {
+                changes.push(next_change.clone());
+            }
+        }
+
+        MetadataInfo {
+            changes,
+            inode: self.inode.then(&next.inode),
+            created: self.created.then(&next.created),
+            modified: self.modified.then(&next.modified),
+            accessed: self.accessed.then(&next.accessed),
+            inode_modified: self.inode_modified.then(&next.inode_modified),
         }
     }
+
+    /// Computes the metadata info describing the transition from `old` to `new`.
+    ///
+    /// Both infos are assumed to describe changes of the same path relative to the same earlier
+    /// state. The resulting info goes from the new values of `old` to the new values of `new`.
+    /// Fields that are not present in the `changes` of one of the infos are assumed to have their
+    /// old value in that info.
+    pub fn diff(
+        old: &MetadataInfo<Timestamp>,
+        new: &MetadataInfo<Timestamp>,
+    ) -> MetadataInfo<Timestamp> {
+        let mut changes = Vec::new();
+
+        for old_change in &old.changes {
+            let change = match new
+                .changes
+                .iter()
+                .find_map(|new_change| old_change.to_new_of(new_change))
+            {
+                Some(change) => change,
+                None => old_change.reversed(),
+            };
+            if !change.is_noop() {
+                changes.push(change);
+            }
+        }
+        for new_change in &new.changes {
+            let in_old = old
+                .changes
+                .iter()
+                .any(|old_change| old_change.to_new_of(new_change).is_some());
+            if !in_old && !new_change.is_noop() {
+                changes.push(new_change.clone());
+            }
+        }
+
+        MetadataInfo {
+            changes,
+            inode: MaybeChange::between(&old.inode, &new.inode),
+            created: MaybeChange::between(&old.created, &new.created),
+            modified: MaybeChange::between(&old.modified, &new.modified),
+            accessed: MaybeChange::between(&old.accessed, &new.accessed),
+            inode_modified: MaybeChange::between(&old.inode_modified, &new.inode_modified),
+        }
+    }
+}
+
+/// Represents a change of a file system entry and its associated metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum MetaEntryDiff<Timestamp> {
+    /// The entry was added.
+    Added(MetadataInfo<Timestamp>),
+    /// The entry was deleted.
+    Deleted(MetadataInfo<Timestamp>),
+    /// Only the metadata changed.
+    MetaOnlyChange(MetadataInfo<Timestamp>),
+    /// The entry changed (and with it likely the metadata too).
+    EntryChange(EntryDiff, MetadataInfo<Timestamp>),
+}
+
+/// Diffs are ordered by their kind first, in the order `Added`, `Deleted`, `MetaOnlyChange` and
+/// `EntryChange`, and then by their contents.
+///
+/// The contents are compared field by field in declaration order, where changes are ordered
+/// before unchanged values and the changes of an info are compared lexicographically.
+impl<Timestamp: Ord> Ord for MetaEntryDiff<Timestamp> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let index = |diff: &MetaEntryDiff<Timestamp>| match diff {
+            MetaEntryDiff::Added(_) => 0,
+            MetaEntryDiff::Deleted(_) => 1,
+            MetaEntryDiff::MetaOnlyChange(_) => 2,
+            MetaEntryDiff::EntryChange(_, _) => 3,
+        };
+
+        match (self, other) {
+            (MetaEntryDiff::Added(a), MetaEntryDiff::Added(b))
+            | (MetaEntryDiff::Deleted(a), MetaEntryDiff::Deleted(b))
+            | (MetaEntryDiff::MetaOnlyChange(a), MetaEntryDiff::MetaOnlyChange(b)) => {
+                a.canonical_cmp(b)
+            }
+            (MetaEntryDiff::EntryChange(entry_a, a), MetaEntryDiff::EntryChange(entry_b, b)) => {
+                entry_a
+                    .canonical_cmp(entry_b)
+                    .then_with(|| a.canonical_cmp(b))
+            }
+            _ => index(self).cmp(&index(other)),
+        }
+    }
+}
+
+impl<Timestamp: Ord> PartialOrd for MetaEntryDiff<Timestamp> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Timestamp> MetaEntryDiff<Timestamp> {
+    /// Returns the enclosed metadata info.
+    pub fn meta_info(&self) -> &MetadataInfo<Timestamp> {
+        match self {
+            MetaEntryDiff::Added(info)
+            | MetaEntryDiff::Deleted(info)
+            | MetaEntryDiff::EntryChange(_, info)
+            | MetaEntryDiff::MetaOnlyChange(info) => info,
+        }
+    }
+
+    /// Returns the enclosed metadata info mutably.
+    pub fn meta_info_mut(&mut self) -> &mut MetadataInfo<Timestamp> {
+        match self {
+            MetaEntryDiff::Added(info)
+            | MetaEntryDiff::Deleted(info)
+            | MetaEntryDiff::EntryChange(_, info)
+            | MetaEntryDiff::MetaOnlyChange(info) => info,
+        }
+    }
+
+    /// Returns whether both diffs are equal when ignoring the timestamps.
+    fn eq_ignoring_timestamps<OtherTimestamp>(
+        &self,
+        other: &MetaEntryDiff<OtherTimestamp>,
+    ) -> bool {
+        match (self, other) {
+            (MetaEntryDiff::Added(a), MetaEntryDiff::Added(b))
+            | (MetaEntryDiff::Deleted(a), MetaEntryDiff::Deleted(b))
+            | (MetaEntryDiff::MetaOnlyChange(a), MetaEntryDiff::MetaOnlyChange(b)) => {
+                a.eq_ignoring_timestamps(b)
+            }
+            (MetaEntryDiff::EntryChange(entry_a, a), MetaEntryDiff::EntryChange(entry_b, b)) => {
+                entry_a == entry_b && a.eq_ignoring_timestamps(b)
+            }
+            _ => false,
+        }
+    }
+
+    /// Transforms the contained timestamps by applying the given function to it.
+    pub fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
+        &self,
+        f: F,
+    ) -> MetaEntryDiff<NewTimestamp> {
+        match self {
+            MetaEntryDiff::Added(meta) => MetaEntryDiff::Added(meta.transform_timestamps(f)),
+            MetaEntryDiff::Deleted(meta) => MetaEntryDiff::Deleted(meta.transform_timestamps(f)),
+            MetaEntryDiff::MetaOnlyChange(meta) => {
+                MetaEntryDiff::MetaOnlyChange(meta.transform_timestamps(f))
+            }
+            MetaEntryDiff::EntryChange(entry, meta) => {
+                MetaEntryDiff::EntryChange(entry.clone(), meta.transform_timestamps(f))
+            }
+        }
+    }
+
+    /// Transforms the contained timestamps by applying the given function to it, consuming
+    /// the diff.
+    pub fn into_transform_timestamps<NewTimestamp, F: FnMut(Timestamp) -> NewTimestamp>(
+        self,
+        f: F,
+    ) -> MetaEntryDiff<NewTimestamp> {
+        match self {
+            MetaEntryDiff::Added(meta) => MetaEntryDiff::Added(meta.into_transform_timestamps(f)),
+            MetaEntryDiff::Deleted(meta) => {
+                MetaEntryDiff::Deleted(meta.into_transform_timestamps(f))
+            }
+            MetaEntryDiff::MetaOnlyChange(meta) => {
+                MetaEntryDiff::MetaOnlyChange(meta.into_transform_timestamps(f))
+            }
+            MetaEntryDiff::EntryChange(entry, meta) => {
+                MetaEntryDiff::EntryChange(entry, meta.into_transform_timestamps(f))
+            }
+        }
+    }
+
+    /// Applies the given function to the enclosed metadata info.
+    pub fn map_metadata<F: FnOnce(MetadataInfo<Timestamp>) -> MetadataInfo<Timestamp>>(
+        self,
+        f: F,
+    ) -> MetaEntryDiff<Timestamp> {
+        match self {
+            MetaEntryDiff::Added(info) => MetaEntryDiff::Added(f(info)),
+            MetaEntryDiff::Deleted(info) => MetaEntryDiff::Deleted(f(info)),
+            MetaEntryDiff::MetaOnlyChange(info) => MetaEntryDiff::MetaOnlyChange(f(info)),
+            MetaEntryDiff::EntryChange(entry, info) => MetaEntryDiff::EntryChange(entry, f(info)),
+        }
+    }
+
+    /// Returns whether only the access control list of the entry changed.
+    ///
+    /// This is the case for a `MetaOnlyChange` where all the `changes` are changes of the
+    /// `NamedStreamType::AccessControlList` stream. Changes of the timestamps or the inode are
+    /// not taken into account, since those are usually updated along with the access control
+    /// list.
+    pub fn is_acl_only_change(&self) -> bool {
+        let MetaEntryDiff::MetaOnlyChange(info) = self else {
+            return false;
+        };
+
+        !info.changes.is_empty()
+            && info.changes.iter().all(|change| {
+                matches!(
+                    change,
+                    MetadataChange::NamedStream(NamedStreamType::AccessControlList, _)
+                )
+            })
+    }
+
+    /// Returns whether the contents of the file changed while it was also newly hidden.
+    ///
+    /// This is the case for an `EntryChange` with `EntryDiff::FileChanged`, where the changes
+    /// include an NTFS attribute change that sets `NtfsAttributes::HIDDEN`.
+    pub fn is_hidden_and_modified(&self) -> bool {
+        let MetaEntryDiff::EntryChange(EntryDiff::FileChanged { .. }, info) = self else {
+            return false;
+        };
+
+        info.changes.iter().any(|change| {
+            change
+                .ntfs_attribute_diff()
+                .is_some_and(|(added, _)| added.contains(NtfsAttributes::HIDDEN))
+        })
+    }
+
+    /// Returns the kind of this diff.
+    pub fn kind(&self) -> MetaEntryDiffKind {
+        match self {
+            MetaEntryDiff::Added(_) => MetaEntryDiffKind::Added,
+            MetaEntryDiff::Deleted(_) => MetaEntryDiffKind::Deleted,
+            MetaEntryDiff::MetaOnlyChange(_) => MetaEntryDiffKind::MetaOnlyChange,
+            MetaEntryDiff::EntryChange(_, _) => MetaEntryDiffKind::EntryChange,
+        }
+    }
+
+    /// Flattens the diff of the given path into attributes for structured logging.
+    ///
+    /// The keys are dot separated and follow the OpenTelemetry naming style: `file.path` and
+    /// `change.kind` are always present, while `file.hash`, `file.symlink_target`, `file.size`,
+    /// `file.mode`, `file.uid` and `file.gid` are present with `.old` and `.new` suffixes if
+    /// they changed. Absent old or new values are left out. Size changes additionally have a
+    /// `file.size.delta` and modes are written in octal.
+    pub fn to_attributes(&self, path: &str) -> std::collections::BTreeMap<String, String> {
+        let mut attributes = std::collections::BTreeMap::new();
+        let mut add_change = |key: &str, from: Option<String>, to: Option<String>| {
+            if let Some(from) = from {
+                attributes.insert(format!("{key}.old"), from);
+            }
+            if let Some(to) = to {
+                attributes.insert(format!("{key}.new"), to);
+            }
+        };
+
+        match self {
+            MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) => add_change(
+                "file.hash",
+                Some(String::from(hash_change.from.clone())),
+                Some(String::from(hash_change.to.clone())),
+            ),
+            MetaEntryDiff::EntryChange(EntryDiff::SymlinkChanged { path_change }, _) => add_change(
+                "file.symlink_target",
+                Some(path_change.from.clone()),
+                Some(path_change.to.clone()),
+            ),
+            _ => (),
+        }
+        for change in &self.meta_info().changes {
+            let to_string = |value: &Option<u32>| value.map(|value| value.to_string());
+            match change {
+                MetadataChange::Size(size) => add_change(
+                    "file.size",
+                    Some(size.from.to_string()),
+                    Some(size.to.to_string()),
+                ),
+                MetadataChange::UnixPermissions(mode) => add_change(
+                    "file.mode",
+                    mode.from.map(|mode| format!("{mode:o}")),
+                    mode.to.map(|mode| format!("{mode:o}")),
+                ),
+                MetadataChange::Uid(uid) => {
+                    add_change("file.uid", to_string(&uid.from), to_string(&uid.to))
+                }
+                MetadataChange::Gid(gid) => {
+                    add_change("file.gid", to_string(&gid.from), to_string(&gid.to))
+                }
+                _ => (),
+            }
+        }
+        let size_delta = self
+            .meta_info()
+            .changes
+            .iter()
+            .find_map(MetadataChange::size_delta);
+        if let Some(delta) = size_delta {
+            attributes.insert("file.size.delta".to_string(), delta.to_string());
+        }
+        attributes.insert("file.path".to_string(), path.to_string());
+        attributes.insert("change.kind".to_string(), self.kind().as_str().to_string());
+
+        attributes
+    }
+
+    /// Returns the operation that this diff represents.
+    pub fn operation(&self) -> Operation {
+        match self {
+            MetaEntryDiff::Added(_) => Operation::Create,
+            MetaEntryDiff::Deleted(_) => Operation::Delete,
+            MetaEntryDiff::MetaOnlyChange(_) => Operation::ModifyMetadata,
+            MetaEntryDiff::EntryChange(EntryDiff::TypeChange(_), _) => Operation::ChangeType,
+            MetaEntryDiff::EntryChange(_, _) => Operation::ModifyContent,
+        }
+    }
+}
+
+impl<Timestamp: PartialEq> MetaEntryDiff<Timestamp> {
+    /// Returns whether the entry was only accessed without being changed otherwise.
+    ///
+    /// This is the case for a `MetaOnlyChange` where the access timestamp changed, but no other
+    /// metadata did. Since reading a file does not update its inode modification timestamp, a
+    /// change of that timestamp alongside the access timestamp is not considered access only.
+    pub fn is_access_only(&self) -> bool {
+        let MetaEntryDiff::MetaOnlyChange(info) = self else {
+            return false;
+        };
+        let changed = |field: &MaybeChange<Option<Timestamp>>| field.old_val() != field.new_val();
+
+        info.changes.is_empty()
+            && info.inode.old_val() == info.inode.new_val()
+            && changed(&info.accessed)
+            && !changed(&info.created)
+            && !changed(&info.modified)
+            && !changed(&info.inode_modified)
+    }
+
+    /// Returns whether both diffs are equal when ignoring the order of the changes.
+    fn semantic_eq(&self, other: &MetaEntryDiff<Timestamp>) -> bool {
+        match (self, other) {
+            (MetaEntryDiff::Added(a), MetaEntryDiff::Added(b))
+            | (MetaEntryDiff::Deleted(a), MetaEntryDiff::Deleted(b))
+            | (MetaEntryDiff::MetaOnlyChange(a), MetaEntryDiff::MetaOnlyChange(b)) => {
+                a.semantic_eq(b)
+            }
+            (MetaEntryDiff::EntryChange(entry_a, a), MetaEntryDiff::EntryChange(entry_b, b)) => {
+                entry_a == entry_b && a.semantic_eq(b)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<Timestamp: Clone + PartialEq> MetaEntryDiff<Timestamp> {
+    /// Returns the diff of `self` followed by `next`.
+    ///
+    /// If the entry ends up not being changed at all, such as when it was added and deleted
+    /// again, `Ok(None)` is returned. See `MergeConflictKind` for the cases that result in an
+    /// error.
+    fn then(
+        &self,
+        next: &MetaEntryDiff<Timestamp>,
+    ) -> Result<Option<MetaEntryDiff<Timestamp>>, MergeConflictKind> {
+        let diff = match (self, next) {
+            (MetaEntryDiff::Added(_), MetaEntryDiff::Deleted(_)) => return Ok(None),
+            (
+                MetaEntryDiff::Added(a),
+                MetaEntryDiff::MetaOnlyChange(b) | MetaEntryDiff::EntryChange(_, b),
+            ) => MetaEntryDiff::Added(a.then(b)),
+            (MetaEntryDiff::Deleted(a), MetaEntryDiff::Added(b)) => {
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, MetadataInfo::diff(a, b))
+            }
+            (MetaEntryDiff::Deleted(_), _) => return Err(MergeConflictKind::ChangedAfterDeletion),
+            (_, MetaEntryDiff::Added(_)) => return Err(MergeConflictKind::AddedWhileExisting),
+            (_, MetaEntryDiff::Deleted(b)) => MetaEntryDiff::Deleted(b.clone()),
+            (MetaEntryDiff::MetaOnlyChange(a), MetaEntryDiff::MetaOnlyChange(b)) => {
+                MetaEntryDiff::MetaOnlyChange(a.then(b))
+            }
+            (MetaEntryDiff::MetaOnlyChange(a), MetaEntryDiff::EntryChange(entry, b))
+            | (MetaEntryDiff::EntryChange(entry, a), MetaEntryDiff::MetaOnlyChange(b)) => {
+                MetaEntryDiff::EntryChange(entry.clone(), a.then(b))
+            }
+            (MetaEntryDiff::EntryChange(entry_a, a), MetaEntryDiff::EntryChange(entry_b, b)) => {
+                match entry_a.then(entry_b) {
+                    Some(Some(entry)) => MetaEntryDiff::EntryChange(entry, a.then(b)),
+                    Some(None) => MetaEntryDiff::MetaOnlyChange(a.then(b)),
+                    None => return Err(MergeConflictKind::Discontinuous),
+                }
+            }
+        };
+
+        Ok(Some(diff))
+    }
+}
+
+/// Aggregations over iterators of diffs.
+///
+/// This is implemented for all iterators over references to diffs, so the aggregations can be
+/// combined with arbitrary filters, for example over `changes.values()` of a `Changeset`.
+pub trait MetaEntryDiffExt<'a, Timestamp: 'a>:
+    Iterator<Item = &'a MetaEntryDiff<Timestamp>> + Sized
+{
+    /// Counts the diffs of each kind.
+    ///
+    /// The counts are indexed in the order in which the kinds are declared in
+    /// `MetaEntryDiffKind`: added, deleted, metadata only and entry changes.
+    fn kind_counts(self) -> [usize; 4] {
+        let mut counts = [0; 4];
+        for diff in self {
+            counts[diff.kind() as usize] += 1;
+        }
+
+        counts
+    }
+
+    /// Returns the sum of the signed differences of all size changes.
+    fn total_size_delta(self) -> i128 {
+        self.flat_map(|diff| &diff.meta_info().changes)
+            .filter_map(MetadataChange::size_delta)
+            .sum()
+    }
+
+    /// Counts the diffs where the contents of a file changed.
+    fn content_changes(self) -> usize {
+        self.filter(|diff| {
+            matches!(
+                diff,
+                MetaEntryDiff::EntryChange(EntryDiff::FileChanged { .. }, _)
+            )
+        })
+        .count()
+    }
+}
+
+impl<'a, Timestamp: 'a, I: Iterator<Item = &'a MetaEntryDiff<Timestamp>>>
+    MetaEntryDiffExt<'a, Timestamp> for I
+{
+}
+
+/// The effective operation performed on a file system entry.
+///
+/// This is a flat summary of a `MetaEntryDiff`, suitable for storing as a single small integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[repr(u8)]
+pub enum Operation {
+    /// The entry was created.
+    Create = 0,
+    /// The entry was deleted.
+    Delete = 1,
+    /// The content of the entry was modified.
+    ModifyContent = 2,
+    /// Only the metadata of the entry was modified.
+    ModifyMetadata = 3,
+    /// The type of the entry changed.
+    ChangeType = 4,
+}
+
+impl From<Operation> for u8 {
+    fn from(value: Operation) -> Self {
+        value as u8
+    }
+}
+
+impl TryFrom<u8> for Operation {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Operation::Create),
+            1 => Ok(Operation::Delete),
+            2 => Ok(Operation::ModifyContent),
+            3 => Ok(Operation::ModifyMetadata),
+            4 => Ok(Operation::ChangeType),
+            _ => Err(format!("invalid operation value {value}")),
+        }
+    }
+}
+
+/// The file extensions that are considered executable by default.
+pub const DEFAULT_EXECUTABLE_EXTENSIONS: &[&str] =
+    &["exe", "dll", "sh", "ps1", "bat", "so", "dylib"];
+
+/// Returns the last component of the given path.
+///
+/// Both `/` and `\` are treated as path separators.
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// Returns the parent directory of the given path.
+///
+/// Both `/` and `\` are treated as path separators. The separator is kept for root
+/// directories, such as `/` or `C:\`. Paths without a separator have the empty string as
+/// their parent.
+fn parent_dir(path: &str) -> &str {
+    match path.rfind(['/', '\\']) {
+        Some(idx) if idx == 0 || path[..idx].ends_with(':') => &path[..=idx],
+        Some(idx) => &path[..idx],
+        None => "",
+    }
+}
+
+/// Returns the components of the given path together with the offsets of their ends.
+///
+/// Both `/` and `\` are treated as path separators. Empty components are skipped, except for
+/// the first one, which marks an absolute path.
+fn path_components(path: &str) -> Vec<(&str, usize)> {
+    let mut start = 0;
+    let mut components = Vec::new();
+    for (i, component) in path.split(['/', '\\']).enumerate() {
+        let end = start + component.len();
+        if i == 0 || !component.is_empty() {
+            components.push((component, end));
+        }
+        start = end + 1;
+    }
+
+    components
+}
+
+/// Returns the root of the given path.
+///
+/// For UNC paths such as `\\server\share\file` this is `\\server\share`, for paths starting
+/// with a drive letter such as `C:\file` it is the drive letter followed by a colon (`C:`) and
+/// for absolute Unix paths it is `/`. Relative paths have the empty string as their root.
+fn path_root(path: &str) -> &str {
+    if let Some(rest) = path.strip_prefix("\\\\") {
+        let mut end = 2;
+        for component in rest.splitn(3, ['/', '\\']).take(2) {
+            end += component.len() + 1;
+        }
+        return &path[..std::cmp::min(end - 1, path.len())];
+    }
+    match path
+        .split_once(['/', '\\'])
+        .map_or(path, |(first, _)| first)
+    {
+        "" if !path.is_empty() => &path[..1],
+        first if first.len() == 2 && first.ends_with(':') => first,
+        _ => "",
+    }
+}
+
+/// Returns the extension of the file at the given path, if it has one.
+fn extension(path: &str) -> Option<&str> {
+    match file_name(path).rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext),
+        _ => None,
+    }
+}
+
+/// Returns the schema version of changesets serialized without a version.
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// Returns an error if the schema version is newer than `Changeset::SCHEMA_VERSION`.
+fn check_supported_schema_version(schema_version: u32) -> serde_json::Result<()> {
+    use serde::de::Error as _;
+
+    if schema_version > Changeset::SCHEMA_VERSION {
+        return Err(serde_json::Error::custom(format!(
+            "unsupported schema version {}, at most {} is supported",
+            schema_version,
+            Changeset::SCHEMA_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns whether the path matches the given glob pattern.
+///
+/// A `*` matches any sequence of characters, including path separators, and a `?` matches a
+/// single character. All other characters only match themselves.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // The position of the last `*` in the pattern and the position in the path it matched up to.
+    let mut star = None;
+
+    while t < path.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == path[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Represents a set of changes for a whole diff tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Changeset<Timestamp> {
+    /// The version of the schema the changeset was created with.
+    ///
+    /// Changesets serialized before the version was recorded are treated as version 1.
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    /// The earliest possible in this changeset.
+    pub earliest_timestamp: self::Timestamp,
+    /// All the changes in this change set.
+    pub changes: std::collections::BTreeMap<String, MetaEntryDiff<Timestamp>>,
+    /// Arbitrary annotations of paths, such as notes or verdicts from triaging the changes.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub annotations: std::collections::BTreeMap<String, serde_json::Value>,
+    /// Risk scores of paths from 0 to 100, as computed by `Changeset::score_risks`.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub risk_scores: std::collections::BTreeMap<String, u8>,
+}
+
+impl<Timestamp> Changeset<Timestamp> {
+    /// Transforms the contained timestamps by applying the given function to it.
+    pub fn transform_timestamps<NewTimestamp, F: FnMut(&Timestamp) -> NewTimestamp>(
+        &self,
+        mut f: F,
+    ) -> Changeset<NewTimestamp> {
+        Changeset {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp.clone(),
+            changes: self
+                .changes
+                .iter()
+                .map(|(path, diff)| (path.clone(), diff.transform_timestamps(&mut f)))
+                .collect(),
+            annotations: self.annotations.clone(),
+            risk_scores: self.risk_scores.clone(),
+        }
+    }
+
+    /// Transforms the contained timestamps by applying the given function to it, consuming
+    /// the changeset.
+    ///
+    /// Unlike `transform_timestamps`, this moves the entries instead of cloning them.
+    pub fn into_transform_timestamps<NewTimestamp, F: FnMut(Timestamp) -> NewTimestamp>(
+        self,
+        mut f: F,
+    ) -> Changeset<NewTimestamp> {
+        Changeset {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp,
+            changes: self
+                .changes
+                .into_iter()
+                .map(|(path, diff)| (path, diff.into_transform_timestamps(&mut f)))
+                .collect(),
+            annotations: self.annotations,
+            risk_scores: self.risk_scores,
+        }
+    }
+
+    /// Applies the given function to the metadata info of every entry, together with its path.
+    pub fn map_metadata<F: FnMut(&str, MetadataInfo<Timestamp>) -> MetadataInfo<Timestamp>>(
+        self,
+        mut f: F,
+    ) -> Changeset<Timestamp> {
+        Changeset {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp,
+            changes: self
+                .changes
+                .into_iter()
+                .map(|(path, diff)| {
+                    let diff = diff.map_metadata(|info| f(&path, info));
+                    (path, diff)
+                })
+                .collect(),
+            annotations: self.annotations,
+            risk_scores: self.risk_scores,
+        }
+    }
+
+    /// Returns a changeset with the given changes and the remaining properties of this one.
+    ///
+    /// Only the annotations and risk scores of paths that are present in `changes` are kept.
+    fn with_changes<T>(
+        &self,
+        changes: std::collections::BTreeMap<String, MetaEntryDiff<T>>,
+    ) -> Changeset<T> {
+        Changeset {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp.clone(),
+            annotations: self
+                .annotations
+                .iter()
+                .filter(|(path, _)| changes.contains_key(*path))
+                .map(|(path, value)| (path.clone(), value.clone()))
+                .collect(),
+            risk_scores: self
+                .risk_scores
+                .iter()
+                .filter(|(path, _)| changes.contains_key(*path))
+                .map(|(path, score)| (path.clone(), *score))
+                .collect(),
+            changes,
+        }
+    }
+
+    /// Returns whether both changesets are equal when ignoring all timestamps.
+    ///
+    /// This ignores the `earliest_timestamp` and the timestamps in the metadata of all entries.
+    pub fn eq_ignoring_timestamps(&self, other: &Changeset<Timestamp>) -> bool {
+        self.annotations == other.annotations
+            && self.risk_scores == other.risk_scores
+            && self.changes.len() == other.changes.len()
+            && self
+                .changes
+                .iter()
+                .zip(&other.changes)
+                .all(|((path_a, a), (path_b, b))| path_a == path_b && a.eq_ignoring_timestamps(b))
+    }
+
+    /// Attaches an annotation to the given path, returning the previous annotation.
+    pub fn annotate(
+        &mut self,
+        path: impl Into<String>,
+        value: serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        self.annotations.insert(path.into(), value)
+    }
+
+    /// Returns the annotation of the given path.
+    pub fn annotation(&self, path: &str) -> Option<&serde_json::Value> {
+        self.annotations.get(path)
+    }
+
+    /// Computes the risk score of every entry using the given scorer and stores it.
+    ///
+    /// Scores above 100 are capped at 100. Existing risk scores are replaced.
+    /// `default_risk_score` can be used as a default scorer.
+    pub fn score_risks<F: Fn(&MetaEntryDiff<Timestamp>) -> u8>(&mut self, scorer: F) {
+        self.risk_scores = self
+            .changes
+            .iter()
+            .map(|(path, diff)| (path.clone(), std::cmp::min(scorer(diff), 100)))
+            .collect();
+    }
+
+    /// Returns the risk score of the given path.
+    pub fn risk_score(&self, path: &str) -> Option<u8> {
+        self.risk_scores.get(path).copied()
+    }
+
+    /// Returns the number of changed paths.
+    ///
+    /// This is the preferred way to get the number of changes instead of accessing `changes`
+    /// directly.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns whether there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the change of the given path.
+    ///
+    /// This is the preferred way to look up changes instead of accessing `changes` directly.
+    pub fn get(&self, path: &str) -> Option<&MetaEntryDiff<Timestamp>> {
+        self.changes.get(path)
+    }
+
+    /// Returns whether the given path was changed.
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.changes.contains_key(path)
+    }
+
+    /// Converts the changeset into a vector of its entries.
+    ///
+    /// The entries are ordered by path, the same way as when iterating over `changes`.
+    ///
+    /// Repeatedly scanning the vector is faster than iterating over `changes` if the scan only
+    /// looks at the diffs themselves, such as their kinds. Scans that also read the paths or the
+    /// lists of metadata changes are dominated by those allocations and see little difference.
+    pub fn into_sorted_vec(self) -> Vec<(String, MetaEntryDiff<Timestamp>)> {
+        self.changes.into_iter().collect()
+    }
+
+    /// Returns a vector of references to the entries of the changeset.
+    ///
+    /// The entries are ordered by path, the same way as when iterating over `changes`.
+    pub fn to_sorted_vec(&self) -> Vec<(&String, &MetaEntryDiff<Timestamp>)> {
+        self.changes.iter().collect()
+    }
+
+    /// Iterates over the entries together with the depth of their paths.
+    ///
+    /// The depth is the number of path separators in the path, where both `/` and `\` are
+    /// treated as separators. So `/etc` has a depth of 1 and `/etc/passwd` has a depth of 2.
+    pub fn iter_with_depth(
+        &self,
+    ) -> impl Iterator<Item = (usize, &String, &MetaEntryDiff<Timestamp>)> {
+        self.changes
+            .iter()
+            .map(|(path, diff)| (path.matches(['/', '\\']).count(), path, diff))
+    }
+
+    /// Groups the changed paths by their parent directory.
+    pub fn group_by_parent(&self) -> std::collections::BTreeMap<&str, Vec<&String>> {
+        let mut groups = std::collections::BTreeMap::<_, Vec<_>>::new();
+        for path in self.changes.keys() {
+            groups.entry(parent_dir(path)).or_default().push(path);
+        }
+
+        groups
+    }
+
+    /// Returns the directories where at least `threshold` entries were added.
+    ///
+    /// The directories are returned together with the number of added entries, sorted by that
+    /// number in descending order.
+    pub fn directories_with_many_additions(&self, threshold: usize) -> Vec<(String, usize)> {
+        let mut directories: Vec<_> = self
+            .group_by_parent()
+            .into_iter()
+            .map(|(dir, paths)| {
+                let additions = paths
+                    .iter()
+                    .filter(|path| matches!(self.changes[path.as_str()], MetaEntryDiff::Added(_)))
+                    .count();
+                (dir.to_string(), additions)
+            })
+            .filter(|(_, additions)| *additions >= threshold)
+            .collect();
+        directories.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        directories
+    }
+
+    /// Returns the entries with the given kind of diff.
+    pub fn of_kind(
+        &self,
+        kind: MetaEntryDiffKind,
+    ) -> impl Iterator<Item = (&String, &MetaEntryDiff<Timestamp>)> {
+        self.changes
+            .iter()
+            .filter(move |(_, diff)| diff.kind() == kind)
+    }
+
+    /// Returns the paths of all entries where only the access control list changed.
+    ///
+    /// See `MetaEntryDiff::is_acl_only_change` for details.
+    pub fn acl_only_changes(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| diff.is_acl_only_change())
+            .map(|(path, _)| path)
+    }
+
+    /// Returns the paths of all entries where the encrypted file system info changed.
+    ///
+    /// Such a change means that the file was encrypted or decrypted, which is typical for
+    /// ransomware. Use `Iterator::count` on the result to get the number of affected entries.
+    pub fn efs_changes(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| {
+                diff.meta_info().changes.iter().any(|change| {
+                    matches!(
+                        change,
+                        MetadataChange::NamedStream(NamedStreamType::EncryptedFileSystemInfo, _)
+                    )
+                })
+            })
+            .map(|(path, _)| path)
+    }
+
+    /// Counts how often each alternate data stream name appears across all entries.
+    ///
+    /// Every change of an alternate data stream counts once, so a name appearing in several
+    /// changes of the same entry is counted multiple times.
+    pub fn ads_name_frequency(&self) -> std::collections::BTreeMap<String, usize> {
+        let mut counts = std::collections::BTreeMap::new();
+        for change in self
+            .changes
+            .values()
+            .flat_map(|diff| &diff.meta_info().changes)
+        {
+            if let MetadataChange::NamedStream(NamedStreamType::AlternateDataStream { name }, _) =
+                change
+            {
+                *counts.entry(name.clone()).or_default() += 1;
+            }
+        }
+
+        counts
+    }
+
+    /// Returns the paths that changed in both changesets, together with both of their diffs.
+    pub fn common_paths<'a>(
+        &'a self,
+        other: &'a Changeset<Timestamp>,
+    ) -> impl Iterator<
+        Item = (
+            &'a String,
+            &'a MetaEntryDiff<Timestamp>,
+            &'a MetaEntryDiff<Timestamp>,
+        ),
+    > {
+        self.changes
+            .iter()
+            .filter_map(|(path, diff)| Some((path, diff, other.changes.get(path)?)))
+    }
+
+    /// Returns the added entries that have a creation timestamp, together with that timestamp.
+    ///
+    /// This separates newly created entries from existing entries that only became visible.
+    pub fn newly_created(&self) -> impl Iterator<Item = (&String, &Timestamp)> {
+        self.changes.iter().filter_map(|(path, diff)| match diff {
+            MetaEntryDiff::Added(info) => info.created.new_val().as_ref().map(|ts| (path, ts)),
+            _ => None,
+        })
+    }
+
+    /// Returns the added entries with a size of zero.
+    ///
+    /// The size of an added entry is recorded as a `MetadataChange::Size` going from `0` to
+    /// the size of the entry, so the new value of that change is used. Since a change from `0`
+    /// to `0` is not recorded, added entries without a size change are considered empty too.
+    pub fn created_empty_files(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| match diff {
+                MetaEntryDiff::Added(info) => info.changes.iter().all(|change| match change {
+                    MetadataChange::Size(change) => change.to == 0,
+                    _ => true,
+                }),
+                _ => false,
+            })
+            .map(|(path, _)| path)
+    }
+
+    /// Counts the entries of each kind.
+    ///
+    /// The counts are indexed in the order in which the kinds are declared in
+    /// `MetaEntryDiffKind`: added, deleted, metadata only and entry changes.
+    pub fn kind_counts(&self) -> [usize; 4] {
+        self.changes.values().kind_counts()
+    }
+
+    /// Returns the fraction of entries whose contents or structure changed.
+    ///
+    /// These are all entries except for `MetaEntryDiff::MetaOnlyChange` entries. For an empty
+    /// changeset `0.0` is returned.
+    pub fn content_ratio(&self) -> f64 {
+        if self.changes.is_empty() {
+            return 0.0;
+        }
+
+        let meta_only = self.kind_count(MetaEntryDiffKind::MetaOnlyChange);
+        (self.changes.len() - meta_only) as f64 / self.changes.len() as f64
+    }
+
+    /// Counts the entries of the given kind.
+    pub fn kind_count(&self, kind: MetaEntryDiffKind) -> usize {
+        self.changes
+            .values()
+            .filter(|diff| diff.kind() == kind)
+            .count()
+    }
+
+    /// Counts the changes grouped by the lowercased file extension of their paths.
+    ///
+    /// Only the last extension is considered, so `archive.tar.gz` is counted as `gz`. Paths
+    /// without an extension are counted under the empty string.
+    pub fn stats_by_extension(&self) -> std::collections::BTreeMap<String, ChangeKindCounts> {
+        let mut stats = std::collections::BTreeMap::<_, ChangeKindCounts>::new();
+        for (path, diff) in &self.changes {
+            let counts = stats
+                .entry(extension(path).unwrap_or_default().to_lowercase())
+                .or_default();
+            match diff {
+                MetaEntryDiff::Added(_) => counts.added += 1,
+                MetaEntryDiff::Deleted(_) => counts.deleted += 1,
+                MetaEntryDiff::MetaOnlyChange(_) | MetaEntryDiff::EntryChange(_, _) => {
+                    counts.modified += 1
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Returns the entries whose owning user or group changed.
+    ///
+    /// Each path is returned together with the change of the user id and the change of the
+    /// group id, at least one of which is present.
+    pub fn ownership_changes(&self) -> impl Iterator<Item = OwnershipChange<'_>> {
+        self.changes.iter().filter_map(|(path, diff)| {
+            let changes = &diff.meta_info().changes;
+            let uid = changes.iter().find_map(|change| match change {
+                MetadataChange::Uid(change) => Some(change),
+                _ => None,
+            });
+            let gid = changes.iter().find_map(|change| match change {
+                MetadataChange::Gid(change) => Some(change),
+                _ => None,
+            });
+
+            (uid.is_some() || gid.is_some()).then_some((path, uid, gid))
+        })
+    }
+
+    /// Returns likely renames as pairs of the old path and the new path.
+    ///
+    /// Added and deleted entries do not carry the hashes of their contents, so a deleted entry
+    /// and an added entry are matched by their inode instead, which is kept when an entry is
+    /// renamed within a file system. If several deleted or added entries share the same inode,
+    /// for example because of hard links, the match is ambiguous and none of them are returned.
+    pub fn detect_renames(&self) -> Vec<(String, String)> {
+        let mut candidates = std::collections::BTreeMap::<u64, (Vec<&String>, Vec<&String>)>::new();
+        for (path, diff) in &self.changes {
+            match diff {
+                MetaEntryDiff::Deleted(info) => {
+                    if let Some(inode) = info.inode.old_val() {
+                        candidates.entry(*inode).or_default().0.push(path);
+                    }
+                }
+                MetaEntryDiff::Added(info) => {
+                    if let Some(inode) = info.inode.new_val() {
+                        candidates.entry(*inode).or_default().1.push(path);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut renames: Vec<_> = candidates
+            .into_values()
+            .filter_map(|(deleted, added)| match (&deleted[..], &added[..]) {
+                ([old], [new]) => Some(((*old).clone(), (*new).clone())),
+                _ => None,
+            })
+            .collect();
+        renames.sort();
+
+        renames
+    }
+
+    /// Returns the paths of existing entries whose inode changed while their contents did not.
+    ///
+    /// These are `MetaOnlyChange` and `EntryChange` entries, where the inode is a
+    /// `MaybeChange::Change` with different old and new values and the entry diff is not an
+    /// `EntryDiff::FileChanged`. This usually means the file was copied across file systems or
+    /// restored from a backup.
+    pub fn inode_changed_same_content(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| match diff {
+                MetaEntryDiff::MetaOnlyChange(info) => info.inode.old_val() != info.inode.new_val(),
+                MetaEntryDiff::EntryChange(EntryDiff::FileChanged { .. }, _) => false,
+                MetaEntryDiff::EntryChange(_, info) => info.inode.old_val() != info.inode.new_val(),
+                _ => false,
+            })
+            .map(|(path, _)| path)
+    }
+
+    /// Groups the paths of entries that changed their type by the old and the new type.
+    ///
+    /// The types are the descriptions contained in `EntryDiff::TypeChange`.
+    pub fn type_transitions(&self) -> std::collections::BTreeMap<(String, String), Vec<&String>> {
+        let mut transitions = std::collections::BTreeMap::<_, Vec<_>>::new();
+        for (path, diff) in &self.changes {
+            if let MetaEntryDiff::EntryChange(EntryDiff::TypeChange(change), _) = diff {
+                transitions
+                    .entry((change.from.clone(), change.to.clone()))
+                    .or_default()
+                    .push(path);
+            }
+        }
+
+        transitions
+    }
+
+    /// Groups the paths by their new inode.
+    ///
+    /// Deleted entries and entries without a new inode are left out. Paths sharing an inode
+    /// are hard links of each other.
+    pub fn paths_by_inode(&self) -> std::collections::BTreeMap<u64, Vec<&String>> {
+        let mut paths = std::collections::BTreeMap::<_, Vec<_>>::new();
+        for (path, diff) in &self.changes {
+            if let MetaEntryDiff::Deleted(_) = diff {
+                continue;
+            }
+            if let Some(inode) = diff.meta_info().inode.new_val() {
+                paths.entry(*inode).or_default().push(path);
+            }
+        }
+
+        paths
+    }
+
+    /// Renders the affected directory tree as a graph in the Graphviz DOT language.
+    ///
+    /// Directories are drawn as folders and changed entries are colored by their kind of
+    /// change: green for added, red for deleted, blue for metadata only and orange for entry
+    /// changes. Each tree starts at the root of its paths, such as `/` or `C:`.
+    pub fn to_dot(&self) -> String {
+        use std::fmt::Write as _;
+
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut nodes = std::collections::BTreeMap::new();
+        let mut edges = std::collections::BTreeSet::new();
+        for (path, diff) in &self.changes {
+            // Seeding the tree with the root keeps `/dir` and `dir` apart.
+            let root = path_root(path);
+            let mut parent = (!root.is_empty()).then(|| {
+                nodes.entry(root.to_string()).or_insert((root, None));
+                root.to_string()
+            });
+            for component in path[root.len()..]
+                .split(['/', '\\'])
+                .filter(|c| !c.is_empty())
+            {
+                let id = match &parent {
+                    Some(parent) if parent.ends_with(['/', '\\']) => format!("{parent}{component}"),
+                    Some(parent) => format!("{parent}/{component}"),
+                    None => component.to_string(),
+                };
+                nodes.entry(id.clone()).or_insert((component, None));
+                if let Some(parent) = parent {
+                    edges.insert((parent, id.clone()));
+                }
+                parent = Some(id);
+            }
+
+            if let Some(id) = parent {
+                let color = match diff {
+                    MetaEntryDiff::Added(_) => "green",
+                    MetaEntryDiff::Deleted(_) => "red",
+                    MetaEntryDiff::MetaOnlyChange(_) => "blue",
+                    MetaEntryDiff::EntryChange(_, _) => "orange",
+                };
+                nodes.get_mut(&id).unwrap().1 = Some(color);
+            }
+        }
+
+        let mut dot = String::from("digraph changeset {\n");
+        for (id, (label, color)) in &nodes {
+            let style = match color {
+                Some(color) => format!("color={color}"),
+                None => "shape=folder".to_string(),
+            };
+            writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\", {style}];",
+                escape(id),
+                escape(label)
+            )
+            .unwrap();
+        }
+        for (from, to) in &edges {
+            writeln!(dot, "    \"{}\" -> \"{}\";", escape(from), escape(to)).unwrap();
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Returns the longest path shared by all changed paths, compared component by component.
+    ///
+    /// The last component of each path names the changed entry itself, so it is never part of
+    /// the result. This makes the result the deepest directory containing all changed entries,
+    /// even if there is only a single one. A path consisting of only a root, such as `/`, is
+    /// its own common root.
+    ///
+    /// Both `/` and `\` are treated as path separators. If there are no changes or the paths
+    /// do not share a root, such as when they are on different drives, `None` is returned.
+    pub fn common_root(&self) -> Option<String> {
+        let parent_len = |components: &[(&str, usize)]| match components {
+            [(root, _)] if root.is_empty() || root.ends_with(':') => 1,
+            _ => components.len() - 1,
+        };
+
+        let mut paths = self.changes.keys();
+        let first = paths.next()?;
+        let first_components = path_components(first);
+        let mut common = parent_len(&first_components);
+        for path in paths {
+            let components = path_components(path);
+            common = components[..parent_len(&components)]
+                .iter()
+                .zip(&first_components[..common])
+                .take_while(|((a, _), (b, _))| a == b)
+                .count();
+        }
+
+        let (root, root_end) = *first_components.first()?;
+        match common {
+            0 => None,
+            1 if root.is_empty() || root.ends_with(':') => {
+                Some(first.get(..=root_end).unwrap_or(root).to_string())
+            }
+            _ => Some(first[..first_components[common - 1].1].to_string()),
+        }
+    }
+
+    /// Returns the number of metadata changes and timestamp changes of each path.
+    ///
+    /// Added and deleted entries are counted the same way as other entries, so only the
+    /// changes recorded in them are counted and not all of their fields.
+    pub fn change_counts(&self) -> std::collections::BTreeMap<&str, usize> {
+        self.changes
+            .iter()
+            .map(|(path, diff)| {
+                let info = diff.meta_info();
+                let timestamp_changes = info
+                    .timestamp_fields()
+                    .iter()
+                    .filter(|(_, field)| field.is_changed())
+                    .count();
+                (path.as_str(), info.changes.len() + timestamp_changes)
+            })
+            .collect()
+    }
+
+    /// Computes a digest for every directory that contains a changed file.
+    ///
+    /// Only files whose contents changed have a known hash, so only `EntryDiff::FileChanged`
+    /// entries are taken into account. The digest of a directory is the SHA-256 hash over all of
+    /// its descendant files in path order, where each file contributes its UTF-8 encoded path, a
+    /// zero byte and the 32 bytes of its new hash. The directories are all proper prefixes of the
+    /// paths, with both `/` and `\` being treated as path separators.
+    pub fn directory_digests(&self) -> std::collections::BTreeMap<String, Hash> {
+        use sha2::Digest as _;
+
+        let mut hashers = std::collections::BTreeMap::<&str, sha2::Sha256>::new();
+        for (path, diff) in &self.changes {
+            let MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) = diff else {
+                continue;
+            };
+
+            let components = path_components(path);
+            let directories = &components[..components.len() - 1];
+            for (i, (component, end)) in directories.iter().enumerate() {
+                let dir = if i == 0 && (component.is_empty() || component.ends_with(':')) {
+                    path.get(..=*end).unwrap_or(component)
+                } else {
+                    &path[..*end]
+                };
+                let hasher = hashers.entry(dir).or_default();
+                hasher.update(path.as_bytes());
+                hasher.update([0]);
+                hasher.update(hash_change.to.as_bytes());
+            }
+        }
+
+        hashers
+            .into_iter()
+            .map(|(dir, hasher)| (dir.to_string(), Hash(hasher.finalize().into())))
+            .collect()
+    }
+
+    /// Returns the paths of changed files whose new hash is one of the given indicators of
+    /// compromise, together with the matched hash.
+    ///
+    /// Only `EntryDiff::FileChanged` entries are considered, since added entries do not carry
+    /// the hashes of their contents.
+    pub fn match_iocs<'a>(
+        &'a self,
+        iocs: &'a std::collections::HashSet<Hash>,
+    ) -> impl Iterator<Item = (&'a String, &'a Hash)> {
+        self.changes.iter().filter_map(|(path, diff)| match diff {
+            MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _)
+                if iocs.contains(&hash_change.to) =>
+            {
+                Some((path, &hash_change.to))
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the paths of entries whose new size is between `min` and `max`, inclusively.
+    ///
+    /// The new size is the new value of the `MetadataChange::Size` change of an entry. Added
+    /// entries without a size change have a size of zero (see `created_empty_files`). Deleted
+    /// entries have no new size and entries whose size did not change have no known size, so
+    /// both are never returned.
+    pub fn entries_in_size_range(&self, min: u64, max: u64) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(move |(_, diff)| {
+                let size = diff
+                    .meta_info()
+                    .changes
+                    .iter()
+                    .find_map(|change| match change {
+                        MetadataChange::Size(change) => Some(change.to),
+                        _ => None,
+                    });
+                let size = match diff {
+                    MetaEntryDiff::Added(_) => Some(size.unwrap_or(0)),
+                    MetaEntryDiff::Deleted(_) => None,
+                    _ => size,
+                };
+                size.is_some_and(|size| (min..=max).contains(&size))
+            })
+            .map(|(path, _)| path)
+    }
+
+    /// Returns the `n` entries with the largest size changes, together with the signed change.
+    ///
+    /// The entries are sorted by the magnitude of the change in descending order. Entries
+    /// without a size change are not included.
+    pub fn top_size_changes(&self, n: usize) -> Vec<(&String, i128)> {
+        let mut changes: Vec<_> = self
+            .changes
+            .iter()
+            .filter_map(|(path, diff)| {
+                let delta = diff
+                    .meta_info()
+                    .changes
+                    .iter()
+                    .find_map(MetadataChange::size_delta)?;
+                Some((path, delta))
+            })
+            .collect();
+        changes.sort_by_key(|(_, delta)| std::cmp::Reverse(delta.unsigned_abs()));
+        changes.truncate(n);
+
+        changes
+    }
+
+    /// Redacts the new hashes of changed files, keeping only the old ones.
+    ///
+    /// The redacted hashes are replaced by `Hash::NULL`, which serves as a marker for a removed
+    /// hash.
+    pub fn keep_only_old_hashes(&mut self) {
+        self.redact_hashes(|hash_change| &mut hash_change.to);
+    }
+
+    /// Redacts the old hashes of changed files, keeping only the new ones.
+    ///
+    /// See `keep_only_old_hashes` for the marker of a removed hash.
+    pub fn keep_only_new_hashes(&mut self) {
+        self.redact_hashes(|hash_change| &mut hash_change.from);
+    }
+
+    /// Replaces the hashes selected by the given function with the marker for a removed hash.
+    fn redact_hashes<F: Fn(&mut Change<Hash>) -> &mut Hash>(&mut self, f: F) {
+        for diff in self.changes.values_mut() {
+            if let MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) = diff {
+                *f(hash_change) = Hash::NULL;
+            }
+        }
+    }
+
+    /// Deduplicates identical named stream data, so that it is only stored once in memory.
+    pub fn intern_streams(&mut self) {
+        let mut seen = std::collections::HashSet::<Arc<[u8]>>::new();
+
+        for diff in self.changes.values_mut() {
+            for change in &mut diff.meta_info_mut().changes {
+                if let MetadataChange::NamedStream(_, change) = change {
+                    for data in [&mut change.from, &mut change.to].into_iter().flatten() {
+                        match seen.get(data) {
+                            Some(existing) => *data = Arc::clone(existing),
+                            None => {
+                                seen.insert(Arc::clone(data));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the total number of bytes of named stream data in the changeset.
+    ///
+    /// Both the old and the new data of every named stream change are counted. Data that is
+    /// shared between entries (see `intern_streams`) is counted once for every occurrence,
+    /// since it is serialized that way.
+    pub fn named_stream_bytes(&self) -> usize {
+        self.changes
+            .values()
+            .flat_map(|diff| &diff.meta_info().changes)
+            .filter_map(|change| match change {
+                MetadataChange::NamedStream(_, change) => Some(change),
+                _ => None,
+            })
+            .flat_map(|change| [&change.from, &change.to].into_iter().flatten())
+            .map(|data| data.len())
+            .sum()
+    }
+
+    /// Coalesces multiple changes of the same named stream in every entry.
+    ///
+    /// See `MetadataInfo::coalesce_named_streams` for details.
+    pub fn coalesce_named_streams(&mut self) {
+        for diff in self.changes.values_mut() {
+            diff.meta_info_mut().coalesce_named_streams();
+        }
+    }
+
+    /// Applies the changeset to a snapshot of the state before the changes.
+    ///
+    /// Added entries are inserted, deleted entries are removed and the hashes, sizes and
+    /// metadata of changed entries are updated. Added entries without a size change have a
+    /// size of zero and no hash, since added entries do not carry hashes.
+    ///
+    /// Every old value in the changeset is checked against the snapshot. Mismatches are
+    /// reported as errors, but the new values are applied nevertheless, so that the snapshot
+    /// reflects the state after the changes as closely as possible. Changes of named streams
+    /// and unknown changes are ignored.
+    pub fn apply_to_snapshot(&self, baseline: &mut Snapshot) -> Result<(), Vec<ApplyError>> {
+        let mut errors = Vec::new();
+
+        for (path, diff) in &self.changes {
+            let mut report = |kind| {
+                errors.push(ApplyError {
+                    path: path.clone(),
+                    kind,
+                })
+            };
+            let info = match diff {
+                MetaEntryDiff::Added(info) => {
+                    if baseline.entries.contains_key(path) {
+                        report(ApplyErrorKind::AlreadyPresent);
+                    }
+                    let mut entry = SnapshotEntry::default();
+                    for change in &info.changes {
+                        entry.apply(change);
+                    }
+                    baseline.entries.insert(path.clone(), entry);
+                    continue;
+                }
+                MetaEntryDiff::Deleted(_) => {
+                    if baseline.entries.remove(path).is_none() {
+                        report(ApplyErrorKind::Missing);
+                    }
+                    continue;
+                }
+                MetaEntryDiff::MetaOnlyChange(info) | MetaEntryDiff::EntryChange(_, info) => info,
+            };
+
+            let Some(entry) = baseline.entries.get_mut(path) else {
+                report(ApplyErrorKind::Missing);
+                continue;
+            };
+            match diff {
+                MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) => {
+                    if entry.hash.as_ref() != Some(&hash_change.from) {
+                        report(ApplyErrorKind::Mismatch(SnapshotField::Hash));
+                    }
+                    entry.hash = Some(hash_change.to.clone());
+                }
+                MetaEntryDiff::EntryChange(EntryDiff::TypeChange(_), _) => entry.hash = None,
+                _ => (),
+            }
+            for change in &info.changes {
+                if let Some(field) = entry.apply(change) {
+                    report(ApplyErrorKind::Mismatch(field));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the paths of added entries that are likely executable.
+    ///
+    /// An entry is considered executable if its file extension is one of the given `extensions`
+    /// (compared case insensitively) or if it gained an execute bit in its unix permissions.
+    /// `DEFAULT_EXECUTABLE_EXTENSIONS` can be used as a default list of extensions.
+    pub fn added_executables<'a>(
+        &'a self,
+        extensions: &'a [&str],
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        self.changes
+            .iter()
+            .filter(move |(path, diff)| {
+                let MetaEntryDiff::Added(info) = diff else {
+                    return false;
+                };
+
+                let has_extension = extension(path).is_some_and(|ext| {
+                    extensions
+                        .iter()
+                        .any(|candidate| candidate.eq_ignore_ascii_case(ext))
+                });
+                let gained_execute = info.changes.iter().any(|change| match change {
+                    MetadataChange::UnixPermissions(change) => {
+                        let is_executable = |mode: Option<u32>| mode.unwrap_or(0) & 0o111 != 0;
+                        !is_executable(change.from) && is_executable(change.to)
+                    }
+                    _ => false,
+                });
+
+                has_extension || gained_execute
+            })
+            .map(|(path, _)| path)
+    }
+}
+
+impl<Timestamp: Clone> Changeset<Timestamp> {
+    /// Returns a changeset containing only the given paths.
+    ///
+    /// Paths that are not part of this changeset are ignored. The earliest timestamp is kept
+    /// as is, since it describes the snapshot and not the individual entries.
+    pub fn subset<'a, I: IntoIterator<Item = &'a str>>(&self, paths: I) -> Changeset<Timestamp> {
+        self.with_changes(
+            paths
+                .into_iter()
+                .filter_map(|path| self.changes.get_key_value(path))
+                .map(|(path, diff)| (path.clone(), diff.clone()))
+                .collect(),
+        )
+    }
+
+    /// Returns a changeset containing only the metadata changes in the given categories.
+    ///
+    /// Entries without any such change are left out. Of the remaining entries only the changes
+    /// in the given categories are kept, while the timestamps and the inode are kept as is.
+    pub fn filter_by_category(
+        &self,
+        categories: &[MetadataChangeCategory],
+    ) -> Changeset<Timestamp> {
+        self.with_changes(
+            self.changes
+                .iter()
+                .filter(|(_, diff)| {
+                    diff.meta_info()
+                        .changes
+                        .iter()
+                        .any(|change| categories.contains(&change.category()))
+                })
+                .map(|(path, diff)| {
+                    let mut diff = diff.clone();
+                    diff.meta_info_mut()
+                        .changes
+                        .retain(|change| categories.contains(&change.category()));
+                    (path.clone(), diff)
+                })
+                .collect(),
+        )
+    }
+
+    /// Splits the changeset into one changeset for every root of the changed paths.
+    ///
+    /// The roots are drive letters such as `C:`, UNC shares such as `\\server\share` and `/`
+    /// for Unix paths. Relative paths are grouped under the empty string. Every changeset
+    /// keeps the `earliest_timestamp` and the annotations and risk scores of its entries.
+    pub fn split_by_root(&self) -> std::collections::BTreeMap<String, Changeset<Timestamp>> {
+        let mut roots = std::collections::BTreeMap::<_, std::collections::BTreeMap<_, _>>::new();
+        for (path, diff) in &self.changes {
+            roots
+                .entry(path_root(path))
+                .or_default()
+                .insert(path.clone(), diff.clone());
+        }
+
+        roots
+            .into_iter()
+            .map(|(root, changes)| (root.to_string(), self.with_changes(changes)))
+            .collect()
+    }
+
+    /// Rewrites all paths in the changeset using the given function.
+    ///
+    /// The function is applied to the changed paths, the annotated paths, the scored paths and
+    /// to the paths inside changed symlinks. It is called only once for each distinct path.
+    ///
+    /// If multiple changed, annotated or scored paths are mapped to the same new path, an error
+    /// is returned.
+    pub fn map_paths<F: FnMut(&str) -> String>(
+        &self,
+        mut f: F,
+    ) -> Result<Changeset<Timestamp>, String> {
+        let mut mapped = std::collections::BTreeMap::new();
+        let mut origins = std::collections::BTreeMap::new();
+        let keys = self
+            .changes
+            .keys()
+            .chain(self.annotations.keys())
+            .chain(self.risk_scores.keys());
+        for path in keys {
+            if mapped.contains_key(path) {
+                continue;
+            }
+            let new_path = f(path);
+            if let Some(existing) = origins.insert(new_path.clone(), path) {
+                return Err(format!(
+                    "paths `{existing}` and `{path}` are both mapped to `{new_path}`"
+                ));
+            }
+            mapped.insert(path.clone(), new_path);
+        }
+
+        let mut changes = std::collections::BTreeMap::new();
+        for (path, diff) in &self.changes {
+            let mut diff = diff.clone();
+            if let MetaEntryDiff::EntryChange(EntryDiff::SymlinkChanged { path_change }, _) =
+                &mut diff
+            {
+                *path_change = path_change.map(|target| {
+                    mapped
+                        .entry(target.clone())
+                        .or_insert_with(|| f(target))
+                        .clone()
+                });
+            }
+            changes.insert(mapped[path].clone(), diff);
+        }
+
+        Ok(Changeset {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp.clone(),
+            changes,
+            annotations: self
+                .annotations
+                .iter()
+                .map(|(path, value)| (mapped[path].clone(), value.clone()))
+                .collect(),
+            risk_scores: self
+                .risk_scores
+                .iter()
+                .map(|(path, score)| (mapped[path].clone(), *score))
+                .collect(),
+        })
+    }
+
+    /// Returns all permission relevant changes in this changeset.
+    ///
+    /// These are changes to the unix permissions, the NTFS attributes, the user or group id and
+    /// the NTFS access control list.
+    pub fn permission_audit(&self) -> Vec<PermissionEvent<Timestamp>> {
+        let mut events = Vec::new();
+
+        for (path, diff) in &self.changes {
+            let info = diff.meta_info();
+            for change in &info.changes {
+                let (field, change) = match change {
+                    MetadataChange::UnixPermissions(change) => (
+                        PermissionField::UnixPermissions,
+                        change.map(|val| val.map(PermissionValue::Number)),
+                    ),
+                    MetadataChange::NtfsAttributes(change) => (
+                        PermissionField::NtfsAttributes,
+                        change.map(|val| val.map(PermissionValue::Number)),
+                    ),
+                    MetadataChange::Uid(change) => (
+                        PermissionField::Uid,
+                        change.map(|val| val.map(PermissionValue::Number)),
+                    ),
+                    MetadataChange::Gid(change) => (
+                        PermissionField::Gid,
+                        change.map(|val| val.map(PermissionValue::Number)),
+                    ),
+                    MetadataChange::NamedStream(NamedStreamType::AccessControlList, change) => (
+                        PermissionField::AccessControlList,
+                        change.map(|val| val.clone().map(PermissionValue::Bytes)),
+                    ),
+                    _ => continue,
+                };
+
+                events.push(PermissionEvent {
+                    path: path.clone(),
+                    field,
+                    change,
+                    timestamp: info.inode_modified.new_val().clone(),
+                });
+            }
+        }
+
+        events
+    }
+
+    /// Converts the changeset into alerts following the common file integrity monitoring schema.
+    ///
+    /// This conversion is lossy, since the schema is less detailed:
+    /// - Metadata-only changes and entry changes are both reported as modifications.
+    /// - Changes of the number of links, named streams other than the access control list and
+    ///   unknown changes do not appear in `changed_attributes`.
+    /// - Symlink changes and type changes do not appear in `changed_attributes`.
+    /// - Hashes are assumed to be SHA-256 hashes and are only available for changed files.
+    pub fn to_fim_alerts(&self) -> Vec<FimAlert<Timestamp>> {
+        self.changes
+            .iter()
+            .map(|(path, diff)| {
+                let info = diff.meta_info();
+                let event = match diff {
+                    MetaEntryDiff::Added(_) => FimEvent::Added,
+                    MetaEntryDiff::Deleted(_) => FimEvent::Deleted,
+                    MetaEntryDiff::MetaOnlyChange(_) | MetaEntryDiff::EntryChange(_, _) => {
+                        FimEvent::Modified
+                    }
+                };
+
+                let mut changed_attributes = Vec::new();
+                let mut add_attribute = |attribute: &str| {
+                    if !changed_attributes
+                        .iter()
+                        .any(|existing| existing == attribute)
+                    {
+                        changed_attributes.push(attribute.to_string());
+                    }
+                };
+                for change in &info.changes {
+                    match change {
+                        MetadataChange::Size(_) => add_attribute("size"),
+                        MetadataChange::NtfsAttributes(_) => add_attribute("attributes"),
+                        MetadataChange::UnixPermissions(_)
+                        | MetadataChange::NamedStream(NamedStreamType::AccessControlList, _) => {
+                            add_attribute("permission")
+                        }
+                        MetadataChange::Uid(_) => add_attribute("uid"),
+                        MetadataChange::Gid(_) => add_attribute("gid"),
+                        MetadataChange::Nlink(_)
+                        | MetadataChange::NamedStream(_, _)
+                        | MetadataChange::Unknown(_) => (),
+                    }
+                }
+                if info.inode.is_changed() {
+                    add_attribute("inode");
+                }
+                if info.modified.is_changed() {
+                    add_attribute("mtime");
+                }
+
+                let (old_sha256, new_sha256) = match diff {
+                    MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) => {
+                        add_attribute("sha256");
+                        (
+                            Some(String::from(hash_change.from.clone())),
+                            Some(String::from(hash_change.to.clone())),
+                        )
+                    }
+                    _ => (None, None),
+                };
+
+                FimAlert {
+                    path: path.clone(),
+                    event,
+                    changed_attributes,
+                    old_sha256,
+                    new_sha256,
+                    timestamp: info.modified.new_val().clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Flattens the changeset into individual change events.
+    ///
+    /// Each entry produces one event for its entry diff, one for each metadata change and one
+    /// for each changed inode or timestamp. Entries without any of those produce a single event
+    /// without a change. The events are produced lazily, one entry at a time.
+    pub fn events(&self) -> impl Iterator<Item = ChangeEvent<Timestamp>> + '_ {
+        self.changes.iter().flat_map(|(path, diff)| {
+            let info = diff.meta_info();
+            let kind = diff.kind();
+
+            let mut changes = Vec::new();
+            if let MetaEntryDiff::EntryChange(entry, _) = diff {
+                changes.push(ChangeEventDetail::Entry(entry.clone()));
+            }
+            changes.extend(
+                info.changes
+                    .iter()
+                    .cloned()
+                    .map(ChangeEventDetail::Metadata),
+            );
+            if let MaybeChange::Change(change) = &info.inode {
+                changes.push(ChangeEventDetail::Inode(change.clone()));
+            }
+            for (timestamp_kind, field) in info.timestamp_fields() {
+                if let MaybeChange::Change(change) = field {
+                    changes.push(ChangeEventDetail::Timestamp(timestamp_kind, change.clone()));
+                }
+            }
+
+            let changes = if changes.is_empty() {
+                vec![None]
+            } else {
+                changes.into_iter().map(Some).collect()
+            };
+            changes.into_iter().map(move |change| ChangeEvent {
+                path: path.clone(),
+                kind,
+                change,
+            })
+        })
+    }
+}
+
+impl<Timestamp: Clone + serde::Serialize> Changeset<Timestamp> {
+    /// Writes the change events of the changeset as newline delimited JSON.
+    ///
+    /// See `events` for details. Every event is written as soon as it is produced, so the
+    /// events are never all held in memory at once.
+    pub fn write_events_ndjson<W: std::io::Write>(&self, w: W) -> std::io::Result<()> {
+        use std::io::Write as _;
+
+        let mut w = std::io::BufWriter::new(w);
+        for event in self.events() {
+            serde_json::to_writer(&mut w, &event)?;
+            w.write_all(b"\n")?;
+        }
+
+        w.flush()
+    }
+}
+
+impl<Timestamp: serde::Serialize> Changeset<Timestamp> {
+    /// Serializes the changeset as pretty printed JSON.
+    pub fn to_pretty_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Serializes the changeset as a JSON value.
+    ///
+    /// This is useful to embed the changeset in a larger JSON document.
+    pub fn to_json_value(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::to_value(self)
+    }
+
+    /// Serializes the changeset as JSON into the given writer.
+    ///
+    /// The writer is buffered internally, so it does not need to be buffered already.
+    pub fn to_writer<W: std::io::Write>(&self, w: W) -> serde_json::Result<()> {
+        use std::io::Write as _;
+
+        let mut w = std::io::BufWriter::new(w);
+        serde_json::to_writer(&mut w, self)?;
+        w.flush().map_err(serde_json::Error::io)
+    }
+
+    /// Serializes the changeset as YAML.
+    ///
+    /// The document has the same structure as the JSON representation, so enums are written as
+    /// maps with a single entry instead of YAML tags. This way unknown metadata changes are
+    /// preserved the same way as in JSON. Hashes and timestamps are written as strings.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> serde_yaml::Result<String> {
+        use serde::ser::Error as _;
+
+        let value = self.to_json_value().map_err(serde_yaml::Error::custom)?;
+        serde_yaml::to_string(&value)
+    }
+}
+
+impl<Timestamp: serde::de::DeserializeOwned> Changeset<Timestamp> {
+    /// Returns an error if the changeset was created with a newer schema version.
+    fn check_schema_version(self) -> serde_json::Result<Self> {
+        check_supported_schema_version(self.schema_version)?;
+
+        Ok(self)
+    }
+
+    /// Deserializes a changeset from JSON.
+    ///
+    /// An error is returned if the changeset was created with a newer schema version than
+    /// `Changeset::SCHEMA_VERSION`. This also applies to the other `from_*` functions.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str::<Self>(json)?.check_schema_version()
+    }
+
+    /// Deserializes a changeset from a JSON value.
+    pub fn from_json_value(value: serde_json::Value) -> serde_json::Result<Self> {
+        serde_json::from_value::<Self>(value)?.check_schema_version()
+    }
+
+    /// Deserializes a changeset from JSON read from the given reader.
+    ///
+    /// The reader is buffered internally, so it does not need to be buffered already.
+    pub fn from_reader<R: std::io::Read>(r: R) -> serde_json::Result<Self> {
+        serde_json::from_reader::<_, Self>(std::io::BufReader::new(r))?.check_schema_version()
+    }
+
+    /// Deserializes a changeset from YAML, as written by `to_yaml`.
+    ///
+    /// Like `from_json`, an error is returned for newer schema versions.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> serde_yaml::Result<Self> {
+        use serde::de::Error as _;
+
+        let value: serde_json::Value = serde_yaml::from_str(yaml)?;
+        Self::from_json_value(value).map_err(serde_yaml::Error::custom)
+    }
+
+    /// Deserializes a changeset from JSON, rejecting unknown fields.
+    ///
+    /// Unlike `from_json`, which silently ignores fields it does not know, this returns an error
+    /// naming the first unknown field. Unknown kinds of metadata changes are rejected as well
+    /// instead of being preserved as `MetadataChange::Unknown`.
+    pub fn from_json_strict(json: &str) -> serde_json::Result<Self> {
+        use serde::de::Error as _;
+
+        let mut unknown = None;
+        let mut de = serde_json::Deserializer::from_str(json);
+        let changeset: Self = serde_ignored::deserialize(&mut de, |path| {
+            unknown.get_or_insert_with(|| path.to_string());
+        })?;
+        de.end()?;
+
+        if let Some(path) = unknown {
+            return Err(serde_json::Error::custom(format!("unknown field `{path}`")));
+        }
+        for (path, diff) in &changeset.changes {
+            for change in &diff.meta_info().changes {
+                if let MetadataChange::Unknown(change) = change {
+                    return Err(serde_json::Error::custom(format!(
+                        "unknown metadata change `{}` at `{path}`",
+                        change.tag
+                    )));
+                }
+            }
+        }
+
+        changeset.check_schema_version()
+    }
+}
+
+impl<Timestamp: PartialEq> Changeset<Timestamp> {
+    /// Returns whether both changesets are equal when ignoring the order of the changes.
+    ///
+    /// Unlike the derived `PartialEq`, the changes of each entry are compared as a multiset, so
+    /// reordering them does not make the changesets unequal.
+    pub fn semantic_eq(&self, other: &Changeset<Timestamp>) -> bool {
+        self.earliest_timestamp == other.earliest_timestamp
+            && self.annotations == other.annotations
+            && self.risk_scores == other.risk_scores
+            && self.changes.len() == other.changes.len()
+            && self
+                .changes
+                .iter()
+                .zip(&other.changes)
+                .all(|((path_a, a), (path_b, b))| path_a == path_b && a.semantic_eq(b))
+    }
+
+    /// Returns the paths of all entries that were only accessed.
+    ///
+    /// See `MetaEntryDiff::is_access_only` for details.
+    pub fn accessed_only(&self) -> impl Iterator<Item = &String> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| diff.is_access_only())
+            .map(|(path, _)| path)
+    }
+}
+
+impl<Timestamp: Eq + std::hash::Hash> Changeset<Timestamp> {
+    /// Groups the paths whose diffs are equal.
+    ///
+    /// This is useful when the same entries were scanned through several paths, for example
+    /// through multiple mount points, so that they can be shown only once.
+    pub fn dedup_by_content(
+        &self,
+    ) -> std::collections::HashMap<&MetaEntryDiff<Timestamp>, Vec<&String>> {
+        let mut groups = std::collections::HashMap::<_, Vec<_>>::new();
+        for (path, diff) in &self.changes {
+            groups.entry(diff).or_default().push(path);
+        }
+
+        groups
+    }
+}
+
+impl<Timestamp: Ord> Changeset<Timestamp> {
+    /// Returns the most recently changed entry.
+    ///
+    /// The entries are compared by their new modification timestamp, falling back to the new
+    /// creation timestamp and then to the new access timestamp. Entries without any of those
+    /// are ignored. If multiple entries share the latest timestamp, the first one in path order
+    /// is returned.
+    pub fn most_recent(&self) -> Option<(&String, &MetaEntryDiff<Timestamp>)> {
+        let mut most_recent: Option<(&Timestamp, (&String, &MetaEntryDiff<Timestamp>))> = None;
+
+        for (path, diff) in &self.changes {
+            if let Some(timestamp) = diff.meta_info().effective_timestamp() {
+                if most_recent.is_none_or(|(latest, _)| timestamp > latest) {
+                    most_recent = Some((timestamp, (path, diff)));
+                }
+            }
+        }
+
+        most_recent.map(|(_, entry)| entry)
+    }
+
+    /// Returns all entries with backdated timestamps, together with the kinds of those.
+    ///
+    /// See `MetadataInfo::backdated_timestamps` for details.
+    pub fn backdated_entries(&self) -> impl Iterator<Item = (&String, Vec<TimestampKind>)> {
+        self.changes.iter().filter_map(|(path, diff)| {
+            let backdated = diff.meta_info().backdated_timestamps();
+            (!backdated.is_empty()).then_some((path, backdated))
+        })
+    }
+}
+
+impl<Timestamp: Clone + PartialEq> Changeset<Timestamp> {
+    /// Merges this changeset with a changeset of the changes that followed it.
+    ///
+    /// See `merge_reporting` for details. Conflicts are resolved silently.
+    pub fn merge(self, other: Changeset<Timestamp>) -> Changeset<Timestamp> {
+        self.merge_reporting(other).0
+    }
+
+    /// Merges this changeset with a changeset of the changes that followed it.
+    ///
+    /// The diffs of paths that are present in both changesets are chained, so that the result
+    /// describes the changes from the state before `self` to the state after `other`. Entries
+    /// that were added and then deleted are left out and entries that were deleted and then
+    /// added again become an `EntryDiff::OtherChange`. An entry that was changed and then
+    /// deleted keeps the metadata of the deletion.
+    ///
+    /// Each path where the diffs could not be chained is reported together with the reason,
+    /// and the diff from `other` is kept for it. The earliest timestamp is the earlier one of
+    /// both changesets and annotations and risk scores from `other` take precedence.
+    pub fn merge_reporting(
+        self,
+        other: Changeset<Timestamp>,
+    ) -> (Changeset<Timestamp>, Vec<MergeConflict>) {
+        let mut changes = self.changes;
+        let mut conflicts = Vec::new();
+
+        for (path, next) in other.changes {
+            let Some(diff) = changes.remove(&path) else {
+                changes.insert(path, next);
+                continue;
+            };
+
+            match diff.then(&next) {
+                Ok(Some(diff)) => {
+                    changes.insert(path, diff);
+                }
+                Ok(None) => (),
+                Err(kind) => {
+                    conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        kind,
+                    });
+                    changes.insert(path, next);
+                }
+            }
+        }
+
+        let mut annotations = self.annotations;
+        annotations.extend(other.annotations);
+        let mut risk_scores = self.risk_scores;
+        risk_scores.extend(other.risk_scores);
+
+        let changeset = Changeset {
+            schema_version: std::cmp::max(self.schema_version, other.schema_version),
+            earliest_timestamp: std::cmp::min(self.earliest_timestamp, other.earliest_timestamp),
+            changes,
+            annotations,
+            risk_scores,
+        };
+        (changeset, conflicts)
+    }
+
+    /// Computes the delta that turns `previous` into this changeset.
+    ///
+    /// The delta only contains the entries, annotations and risk scores that were added,
+    /// removed or changed, so storing a series of similar changesets as deltas needs much less
+    /// space.
+    pub fn delta_from(&self, previous: &Changeset<Timestamp>) -> ChangesetDelta<Timestamp> {
+        /// Returns the values of `current` that differ from `previous` and the removed keys.
+        fn diff_maps<V: Clone + PartialEq>(
+            previous: &std::collections::BTreeMap<String, V>,
+            current: &std::collections::BTreeMap<String, V>,
+        ) -> (
+            std::collections::BTreeMap<String, V>,
+            std::collections::BTreeSet<String>,
+        ) {
+            let changed = current
+                .iter()
+                .filter(|(key, value)| previous.get(*key) != Some(*value))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            let removed = previous
+                .keys()
+                .filter(|key| !current.contains_key(*key))
+                .cloned()
+                .collect();
+            (changed, removed)
+        }
+
+        let (changes, removed) = diff_maps(&previous.changes, &self.changes);
+        let (annotations, removed_annotations) =
+            diff_maps(&previous.annotations, &self.annotations);
+        let (risk_scores, removed_risk_scores) =
+            diff_maps(&previous.risk_scores, &self.risk_scores);
+
+        ChangesetDelta {
+            schema_version: self.schema_version,
+            earliest_timestamp: self.earliest_timestamp.clone(),
+            changes,
+            removed,
+            annotations,
+            removed_annotations,
+            risk_scores,
+            removed_risk_scores,
+        }
+    }
+
+    /// Applies a delta computed by `delta_from` to this changeset.
+    ///
+    /// Applying the delta to the changeset it was computed from results in the changeset it
+    /// was computed for.
+    pub fn apply_delta(&self, delta: &ChangesetDelta<Timestamp>) -> Changeset<Timestamp> {
+        let mut changeset = self.clone();
+        changeset.schema_version = delta.schema_version;
+        changeset.earliest_timestamp = delta.earliest_timestamp.clone();
+        for path in &delta.removed {
+            changeset.changes.remove(path);
+        }
+        changeset.changes.extend(delta.changes.clone());
+        for path in &delta.removed_annotations {
+            changeset.annotations.remove(path);
+        }
+        changeset.annotations.extend(delta.annotations.clone());
+        for path in &delta.removed_risk_scores {
+            changeset.risk_scores.remove(path);
+        }
+        changeset.risk_scores.extend(delta.risk_scores.clone());
+
+        changeset
+    }
+}
+
+impl<Timestamp: Ord + Clone> Changeset<Timestamp> {
+    /// Counts how many entries have each new modification timestamp.
+    ///
+    /// Many entries sharing the exact same timestamp can be a sign of automated tampering.
+    /// Entries without a new modification timestamp are not counted.
+    pub fn timestamp_histogram(&self) -> std::collections::BTreeMap<Timestamp, usize> {
+        let mut histogram = std::collections::BTreeMap::new();
+        for diff in self.changes.values() {
+            if let Some(modified) = diff.meta_info().modified.new_val() {
+                *histogram.entry(modified.clone()).or_default() += 1;
+            }
+        }
+
+        histogram
+    }
+}
+
+impl<Timestamp: Ord + Clone + Into<self::Timestamp>> Changeset<Timestamp> {
+    /// Returns the time span covered by the changeset.
+    ///
+    /// The span starts at the `earliest_timestamp` and ends at the latest new timestamp of
+    /// the entries, using their new modification timestamps, falling back to the new creation
+    /// and access timestamps. If no entry has such a timestamp, `None` is returned.
+    pub fn time_span(&self) -> Option<(self::Timestamp, self::Timestamp)> {
+        let latest = self
+            .changes
+            .values()
+            .filter_map(|diff| diff.meta_info().effective_timestamp())
+            .max()?;
+
+        Some((self.earliest_timestamp.clone(), latest.clone().into()))
+    }
+
+    /// Returns the duration of the time span covered by the changeset.
+    ///
+    /// See `time_span` for details. The duration is negative if all timestamps of the entries
+    /// are before the `earliest_timestamp`, for example because they were backdated.
+    pub fn duration(&self) -> Option<time::Duration> {
+        self.time_span()
+            .map(|(earliest, latest)| *latest - *earliest)
+    }
+
+    /// Counts the entries in fixed-width time buckets aligned to the `earliest_timestamp`.
+    ///
+    /// Every entry is counted in the bucket containing its new modification timestamp, falling
+    /// back to the new creation and access timestamps. Entries without any of those are not
+    /// counted. The buckets are identified by their start and empty buckets are left out.
+    /// Entries before the `earliest_timestamp` are counted in buckets before it. If `bucket`
+    /// is not positive, the result is empty.
+    pub fn timeline(
+        &self,
+        bucket: time::Duration,
+    ) -> std::collections::BTreeMap<self::Timestamp, usize> {
+        let mut timeline = std::collections::BTreeMap::new();
+        if !bucket.is_positive() {
+            return timeline;
+        }
+
+        for diff in self.changes.values() {
+            let Some(timestamp) = diff.meta_info().effective_timestamp() else {
+                continue;
+            };
+            let timestamp: self::Timestamp = timestamp.clone().into();
+            let offset = (*timestamp - *self.earliest_timestamp)
+                .whole_nanoseconds()
+                .rem_euclid(bucket.whole_nanoseconds());
+            let start =
+                self::Timestamp::from(*timestamp - time::Duration::nanoseconds_i128(offset));
+            *timeline.entry(start).or_default() += 1;
+        }
+
+        timeline
+    }
+
+    /// Returns the changeset of all entries that were modified at or after `cutoff`.
+    ///
+    /// An entry is considered to be modified at its new modification timestamp. Entries without
+    /// a new modification timestamp (such as `MaybeChange::Same(None)`) are not included.
+    ///
+    /// The `earliest_timestamp` of the result is recomputed from the remaining entries, as
+    /// described in `Changeset::exclude_allowlist`.
+    pub fn since(&self, cutoff: &Timestamp) -> Changeset<Timestamp> {
+        self.with_changes(self.filter_by_modified(|modified| modified >= cutoff))
+            .with_recomputed_earliest_timestamp()
+    }
+
+    /// Returns the changeset of all entries that were modified at or before `cutoff`.
+    ///
+    /// An entry is considered to be modified at its new modification timestamp. Entries without
+    /// a new modification timestamp (such as `MaybeChange::Same(None)`) are not included.
+    ///
+    /// The `earliest_timestamp` of the result is recomputed from the remaining entries, as
+    /// described in `Changeset::exclude_allowlist`.
+    pub fn until(&self, cutoff: &Timestamp) -> Changeset<Timestamp> {
+        self.with_changes(self.filter_by_modified(|modified| modified <= cutoff))
+            .with_recomputed_earliest_timestamp()
+    }
+
+    /// Returns the changeset without the entries whose paths match any of the given patterns.
+    ///
+    /// The patterns are globs matched against the whole path, where `*` matches any sequence
+    /// of characters, including path separators, and `?` matches a single character. So
+    /// `*.log` matches all paths ending in `.log`.
+    ///
+    /// The `earliest_timestamp` of the result is recomputed from the remaining entries, using
+    /// their new modification timestamps, falling back to the new creation and access
+    /// timestamps. It is never earlier than the current `earliest_timestamp` and it is kept as
+    /// is if no remaining entry has a timestamp.
+    pub fn exclude_allowlist(&self, patterns: &[String]) -> Changeset<Timestamp> {
+        self.with_changes(
+            self.changes
+                .iter()
+                .filter(|(path, _)| !patterns.iter().any(|pattern| glob_matches(pattern, path)))
+                .map(|(path, diff)| (path.clone(), diff.clone()))
+                .collect(),
+        )
+        .with_recomputed_earliest_timestamp()
+    }
+
+    /// Recomputes the `earliest_timestamp` from the effective timestamps of the entries.
+    ///
+    /// The result is never earlier than the current `earliest_timestamp` and it is kept as is
+    /// if no entry has a timestamp.
+    fn with_recomputed_earliest_timestamp(self) -> Changeset<Timestamp> {
+        let earliest = self
+            .changes
+            .values()
+            .filter_map(|diff| diff.meta_info().effective_timestamp())
+            .min()
+            .cloned();
+
+        match earliest {
+            Some(earliest) => Changeset {
+                earliest_timestamp: std::cmp::max(self.earliest_timestamp.clone(), earliest.into()),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Returns the entries whose new modification timestamp matches the given predicate.
+    fn filter_by_modified<F: Fn(&Timestamp) -> bool>(
+        &self,
+        predicate: F,
+    ) -> std::collections::BTreeMap<String, MetaEntryDiff<Timestamp>> {
+        self.changes
+            .iter()
+            .filter(|(_, diff)| {
+                diff.meta_info()
+                    .modified
+                    .new_val()
+                    .as_ref()
+                    .is_some_and(&predicate)
+            })
+            .map(|(path, diff)| (path.clone(), diff.clone()))
+            .collect()
+    }
+}
+
+impl Changeset<Timestamp> {
+    /// The current version of the schema.
+    ///
+    /// It is increased whenever the serialized form of changesets changes in a way that
+    /// consumers need to be aware of.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// Truncates all timestamps in the changeset to the given precision.
+    ///
+    /// This is useful to compare changesets that were produced with different precisions.
+    pub fn truncate_timestamps(&mut self, precision: TimePrecision) {
+        self.earliest_timestamp = self.earliest_timestamp.truncate_to(precision);
+        for diff in self.changes.values_mut() {
+            diff.meta_info_mut()
+                .for_each_timestamp_mut(|ts| *ts = ts.truncate_to(precision));
+        }
+    }
+
+    /// Cleans up the changeset, for example before comparing it with the changeset of another
+    /// run.
+    ///
+    /// The steps are applied in the order of the fields of `NormalizeOptions`. Applying the
+    /// same options a second time does not change the changeset any further.
+    pub fn normalize(&mut self, options: NormalizeOptions) {
+        if let Some(precision) = options.truncate_timestamps {
+            self.truncate_timestamps(precision);
+        }
+        for diff in self.changes.values_mut() {
+            let info = diff.meta_info_mut();
+            if options.collapse_noops {
+                info.changes.retain(|change| !change.is_noop());
+                info.inode.collapse_noop();
+                for field in [
+                    &mut info.created,
+                    &mut info.modified,
+                    &mut info.accessed,
+                    &mut info.inode_modified,
+                ] {
+                    field.collapse_noop();
+                }
+            }
+            if options.dedup_changes {
+                let mut changes = Vec::with_capacity(info.changes.len());
+                for change in std::mem::take(&mut info.changes) {
+                    if !changes.contains(&change) {
+                        changes.push(change);
+                    }
+                }
+                info.changes = changes;
+            }
+        }
+        if options.prune_empty {
+            self.changes.retain(|_, diff| match diff {
+                MetaEntryDiff::MetaOnlyChange(info) => {
+                    !info.changes.is_empty()
+                        || info.inode.is_changed()
+                        || info
+                            .timestamp_fields()
+                            .iter()
+                            .any(|(_, field)| field.is_changed())
+                }
+                _ => true,
+            });
+            let changes = &self.changes;
+            self.annotations
+                .retain(|path, _| changes.contains_key(path));
+            self.risk_scores
+                .retain(|path, _| changes.contains_key(path));
+        }
+    }
+}
+
+/// Returns all paths that were changed in any of the given changesets.
+pub fn touched_paths<T>(sets: &[Changeset<T>]) -> std::collections::BTreeSet<&str> {
+    sets.iter()
+        .flat_map(|set| set.changes.keys())
+        .map(String::as_str)
+        .collect()
+}
+
+/// Computes a risk score from 0 to 100 for the given diff.
+///
+/// Changes of the contents of a file add 40, gaining the setuid or setgid bit adds 50 and
+/// backdated timestamps add 30, with the result capped at 100. Other changes have a score of 0.
+pub fn default_risk_score<Timestamp: Ord>(diff: &MetaEntryDiff<Timestamp>) -> u8 {
+    let info = diff.meta_info();
+    let mut score = 0;
+    if let MetaEntryDiff::EntryChange(EntryDiff::FileChanged { .. }, _) = diff {
+        score += 40;
+    }
+    if info.changes.iter().any(|change| {
+        change
+            .unix_mode_diff()
+            .is_some_and(|diff| diff.sets_privileged_bits())
+    }) {
+        score += 50;
+    }
+    if !info.backdated_timestamps().is_empty() {
+        score += 30;
+    }
+
+    std::cmp::min(score, 100)
+}
+
+/// The variant of a `MetaEntryDiff`, without the data it contains.
+///
+/// Unlike `Operation`, content changes and type changes are not distinguished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum MetaEntryDiffKind {
+    /// The entry was added.
+    Added,
+    /// The entry was deleted.
+    Deleted,
+    /// Only the metadata changed.
+    MetaOnlyChange,
+    /// The entry changed (and with it likely the metadata too).
+    EntryChange,
+}
+
+impl MetaEntryDiffKind {
+    /// Returns the name of the kind in snake case, such as `meta_only_change`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetaEntryDiffKind::Added => "added",
+            MetaEntryDiffKind::Deleted => "deleted",
+            MetaEntryDiffKind::MetaOnlyChange => "meta_only_change",
+            MetaEntryDiffKind::EntryChange => "entry_change",
+        }
+    }
+}
+
+/// The categories of metadata changes.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum MetadataChangeCategory {
+    /// Changes of the size.
+    Size,
+    /// Changes of the Unix permissions, the NTFS attributes or the NTFS access control list.
+    Permissions,
+    /// Changes of the user id or the group id.
+    Ownership,
+    /// Changes of the number of links.
+    Links,
+    /// Changes of named streams other than the access control list.
+    NamedStreams,
+    /// Changes that are not known to this version of the crate.
+    Unknown,
+}
+
+/// The steps performed by `Changeset::normalize`.
+///
+/// By default all steps except the truncation of timestamps are performed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct NormalizeOptions {
+    /// The precision to truncate all timestamps to.
+    pub truncate_timestamps: Option<TimePrecision>,
+    /// Whether to remove metadata changes and to turn timestamp and inode changes into
+    /// unchanged values, if their old and new values are the same.
+    pub collapse_noops: bool,
+    /// Whether to remove metadata changes that occur multiple times in the same entry.
+    pub dedup_changes: bool,
+    /// Whether to remove `MetaEntryDiff::MetaOnlyChange` entries without any changes, together
+    /// with their annotations and risk scores.
+    pub prune_empty: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            truncate_timestamps: None,
+            collapse_noops: true,
+            dedup_changes: true,
+            prune_empty: true,
+        }
+    }
+}
+
+/// The path of an entry together with the changes of its user id and its group id.
+pub type OwnershipChange<'a> = (
+    &'a String,
+    Option<&'a Change<Option<u32>>>,
+    Option<&'a Change<Option<u32>>>,
+);
+
+/// The number of changes of each kind.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct ChangeKindCounts {
+    /// The number of added entries.
+    pub added: usize,
+    /// The number of deleted entries.
+    pub deleted: usize,
+    /// The number of modified entries, either in their contents or their metadata.
+    pub modified: usize,
+}
+
+/// Writes a changeset as JSON one entry at a time.
+///
+/// The result is the same as serializing the whole changeset using `Changeset::to_writer`,
+/// but the entries do not have to be held in memory. Since the earliest timestamp is written
+/// before the entries, it has to be known when creating the writer. The entries have to be
+/// written in strictly ascending order of their paths, which is the order in which they are
+/// stored in a `Changeset`. The document is only complete after calling `finish`.
+pub struct ChangesetWriter<W: std::io::Write> {
+    /// The buffered underlying writer.
+    w: std::io::BufWriter<W>,
+    /// The path of the last written entry.
+    last_path: Option<String>,
+}
+
+impl<W: std::io::Write> ChangesetWriter<W> {
+    /// Creates a new writer and writes the start of the changeset.
+    ///
+    /// The underlying writer is buffered internally, so it does not need to be buffered
+    /// already.
+    pub fn new(w: W, earliest_timestamp: &self::Timestamp) -> std::io::Result<Self> {
+        use std::io::Write as _;
+
+        let mut w = std::io::BufWriter::new(w);
+        write!(w, "{{\"schema_version\":{},", Changeset::SCHEMA_VERSION)?;
+        w.write_all(b"\"earliest_timestamp\":")?;
+        serde_json::to_writer(&mut w, earliest_timestamp)?;
+        w.write_all(b",\"changes\":{")?;
+
+        Ok(ChangesetWriter { w, last_path: None })
+    }
+
+    /// Writes a single entry of the changeset.
+    ///
+    /// An error of kind `std::io::ErrorKind::InvalidInput` is returned if the path is not
+    /// greater than the path of the previous entry.
+    pub fn write_entry<Timestamp: serde::Serialize>(
+        &mut self,
+        path: &str,
+        diff: &MetaEntryDiff<Timestamp>,
+    ) -> std::io::Result<()> {
+        use std::io::Write as _;
+
+        if let Some(last_path) = &self.last_path {
+            if path <= last_path.as_str() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("entry `{path}` is not written after `{last_path}` in order"),
+                ));
+            }
+            self.w.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.w, path)?;
+        self.w.write_all(b":")?;
+        serde_json::to_writer(&mut self.w, diff)?;
+        self.last_path = Some(path.to_string());
+
+        Ok(())
+    }
+
+    /// Writes the end of the changeset and returns the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        use std::io::Write as _;
+
+        self.w.write_all(b"}}")?;
+        self.w
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)
+    }
+}
+
+/// A lightweight overview of a changeset, containing only the paths and the kinds of changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct ChangesetIndex {
+    /// The earliest possible in the changeset.
+    pub earliest_timestamp: self::Timestamp,
+    /// The kinds of all the changes in the changeset.
+    pub changes: std::collections::BTreeMap<String, MetaEntryDiffKind>,
+}
+
+/// Reads the index of a JSON encoded changeset.
+///
+/// The metadata of the entries is skipped without being parsed into values, which makes this
+/// faster and needs less memory than reading the whole changeset. Like `Changeset::from_json`,
+/// an error is returned if the changeset was created with a newer schema version.
+pub fn read_index<R: std::io::BufRead>(r: R) -> serde_json::Result<ChangesetIndex> {
+    use serde::de::IgnoredAny;
+
+    /// A `MetaEntryDiff` with the contents ignored.
+    #[derive(serde::Deserialize)]
+    #[serde(rename = "MetaEntryDiff")]
+    enum IndexedDiff {
+        Added(IgnoredAny),
+        Deleted(IgnoredAny),
+        MetaOnlyChange(IgnoredAny),
+        EntryChange(IgnoredAny, IgnoredAny),
+    }
+
+    /// A `Changeset` with the contents of the entries ignored.
+    #[derive(serde::Deserialize)]
+    #[serde(rename = "Changeset")]
+    struct IndexedChangeset {
+        #[serde(default = "legacy_schema_version")]
+        schema_version: u32,
+        earliest_timestamp: self::Timestamp,
+        changes: std::collections::BTreeMap<String, IndexedDiff>,
+    }
+
+    let indexed: IndexedChangeset = serde_json::from_reader(r)?;
+    check_supported_schema_version(indexed.schema_version)?;
+
+    Ok(ChangesetIndex {
+        earliest_timestamp: indexed.earliest_timestamp,
+        changes: indexed
+            .changes
+            .into_iter()
+            .map(|(path, diff)| {
+                let kind = match diff {
+                    IndexedDiff::Added(_) => MetaEntryDiffKind::Added,
+                    IndexedDiff::Deleted(_) => MetaEntryDiffKind::Deleted,
+                    IndexedDiff::MetaOnlyChange(_) => MetaEntryDiffKind::MetaOnlyChange,
+                    IndexedDiff::EntryChange(_, _) => MetaEntryDiffKind::EntryChange,
+                };
+                (path, kind)
+            })
+            .collect(),
+    })
+}
+
+/// The permission relevant fields of the metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum PermissionField {
+    /// The NTFS attributes.
+    NtfsAttributes,
+    /// The unix permissions.
+    UnixPermissions,
+    /// The user id.
+    Uid,
+    /// The group id.
+    Gid,
+    /// The NTFS access control list.
+    AccessControlList,
+}
+
+/// The value of a permission relevant field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum PermissionValue {
+    /// A numeric value, such as permission bits or an id.
+    Number(u32),
+    /// A raw binary value, such as an access control list.
+    Bytes(Arc<[u8]>),
+}
+
+/// A single permission relevant change of a path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PermissionEvent<Timestamp> {
+    /// The path that was changed.
+    pub path: String,
+    /// The field that was changed.
+    pub field: PermissionField,
+    /// The change of the value of the field.
+    pub change: Change<Option<PermissionValue>>,
+    /// The new inode modification timestamp of the path.
+    ///
+    /// This is used, because changing permissions updates the inode modification timestamp.
+    pub timestamp: Option<Timestamp>,
+}
+
+/// The types of events in the file integrity monitoring schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FimEvent {
+    /// The file was added.
+    Added,
+    /// The file was modified.
+    Modified,
+    /// The file was deleted.
+    Deleted,
+}
+
+/// An alert following the common file integrity monitoring schema.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct FimAlert<Timestamp> {
+    /// The path of the file.
+    pub path: String,
+    /// The type of the event.
+    pub event: FimEvent,
+    /// The names of the changed attributes, such as `size` or `sha256`.
+    pub changed_attributes: Vec<String>,
+    /// The SHA-256 hash of the file before the change as a hex string.
+    pub old_sha256: Option<String>,
+    /// The SHA-256 hash of the file after the change as a hex string.
+    pub new_sha256: Option<String>,
+    /// The new modification timestamp of the file.
+    pub timestamp: Option<Timestamp>,
+}
+
+/// A single change of an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ChangeEventDetail<Timestamp> {
+    /// The entry itself changed.
+    Entry(EntryDiff),
+    /// The metadata changed.
+    Metadata(MetadataChange),
+    /// The inode changed.
+    Inode(Change<Option<u64>>),
+    /// A timestamp changed.
+    Timestamp(TimestampKind, Change<Option<Timestamp>>),
+}
+
+/// A single change of a changeset, flattened into a self contained event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ChangeEvent<Timestamp> {
+    /// The path that was changed.
+    pub path: String,
+    /// The kind of the change of the entry.
+    pub kind: MetaEntryDiffKind,
+    /// The individual change, if the entry has any.
+    pub change: Option<ChangeEventDetail<Timestamp>>,
+}
+
+/// The reasons why the diffs of a path could not be chained when merging changesets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum MergeConflictKind {
+    /// The entry was deleted and then changed or deleted again.
+    ChangedAfterDeletion,
+    /// The entry already existed and was then added.
+    AddedWhileExisting,
+    /// The contents of the file or the target of the symlink at the start of the second diff
+    /// differ from the ones at the end of the first diff.
+    Discontinuous,
+}
+
+/// A path whose diffs could not be chained when merging changesets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct MergeConflict {
+    /// The path of the entry.
+    pub path: String,
+    /// The reason why the diffs could not be chained.
+    pub kind: MergeConflictKind,
+}
+
+/// The difference between two changesets, as computed by `Changeset::delta_from`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ChangesetDelta<Timestamp> {
+    /// The schema version of the newer changeset.
+    pub schema_version: u32,
+    /// The earliest timestamp of the newer changeset.
+    pub earliest_timestamp: self::Timestamp,
+    /// The entries that were added or changed, with their diffs in the newer changeset.
+    pub changes: std::collections::BTreeMap<String, MetaEntryDiff<Timestamp>>,
+    /// The paths of the entries that are only present in the older changeset.
+    pub removed: std::collections::BTreeSet<String>,
+    /// The annotations that were added or changed.
+    pub annotations: std::collections::BTreeMap<String, serde_json::Value>,
+    /// The paths of the annotations that are only present in the older changeset.
+    pub removed_annotations: std::collections::BTreeSet<String>,
+    /// The risk scores that were added or changed.
+    pub risk_scores: std::collections::BTreeMap<String, u8>,
+    /// The paths of the risk scores that are only present in the older changeset.
+    pub removed_risk_scores: std::collections::BTreeSet<String>,
+}
+
+/// The state of a set of entries, as used by `Changeset::apply_to_snapshot`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Snapshot {
+    /// The state of every entry by its path.
+    pub entries: std::collections::BTreeMap<String, SnapshotEntry>,
+}
+
+/// The state of a single entry in a `Snapshot`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct SnapshotEntry {
+    /// The hash of the contents, if the entry is a file with a known hash.
+    pub hash: Option<Hash>,
+    /// The size of the entry.
+    pub size: u64,
+    /// The NTFS attributes of the entry.
+    pub ntfs_attributes: Option<u32>,
+    /// The unix permissions of the entry.
+    pub unix_permissions: Option<u32>,
+    /// The number of links to the entry.
+    pub nlink: Option<u64>,
+    /// The user id of the entry.
+    pub uid: Option<u32>,
+    /// The group id of the entry.
+    pub gid: Option<u32>,
+}
+
+impl SnapshotEntry {
+    /// Applies the metadata change to the entry.
+    ///
+    /// If the old value of the change does not match the entry, the field is returned.
+    fn apply(&mut self, change: &MetadataChange) -> Option<SnapshotField> {
+        /// Sets the new value, returning whether the old value matched.
+        fn set<T: Clone + PartialEq>(value: &mut T, change: &Change<T>) -> bool {
+            let matches = *value == change.from;
+            *value = change.to.clone();
+            matches
+        }
+
+        let (field, matches) = match change {
+            MetadataChange::Size(change) => (SnapshotField::Size, set(&mut self.size, change)),
+            MetadataChange::NtfsAttributes(change) => (
+                SnapshotField::NtfsAttributes,
+                set(&mut self.ntfs_attributes, change),
+            ),
+            MetadataChange::UnixPermissions(change) => (
+                SnapshotField::UnixPermissions,
+                set(&mut self.unix_permissions, change),
+            ),
+            MetadataChange::Nlink(change) => (SnapshotField::Nlink, set(&mut self.nlink, change)),
+            MetadataChange::Uid(change) => (SnapshotField::Uid, set(&mut self.uid, change)),
+            MetadataChange::Gid(change) => (SnapshotField::Gid, set(&mut self.gid, change)),
+            MetadataChange::NamedStream(_, _) | MetadataChange::Unknown(_) => return None,
+        };
+
+        (!matches).then_some(field)
+    }
+}
+
+/// The fields of a `SnapshotEntry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SnapshotField {
+    /// The hash of the contents.
+    Hash,
+    /// The size.
+    Size,
+    /// The NTFS attributes.
+    NtfsAttributes,
+    /// The unix permissions.
+    UnixPermissions,
+    /// The number of links.
+    Nlink,
+    /// The user id.
+    Uid,
+    /// The group id.
+    Gid,
+}
+
+/// The reasons why a changeset could not be applied cleanly to a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ApplyErrorKind {
+    /// The entry was added, but it was already present in the snapshot.
+    AlreadyPresent,
+    /// The entry was deleted or changed, but it was not present in the snapshot.
+    Missing,
+    /// The old value of the field in the changeset does not match the snapshot.
+    Mismatch(SnapshotField),
+}
+
+/// A path where a changeset could not be applied cleanly to a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ApplyError {
+    /// The path of the entry.
+    pub path: String,
+    /// The reason why the changeset could not be applied.
+    pub kind: ApplyErrorKind,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Returns metadata with the given changes and no timestamps or inode.
+    pub(crate) fn info(changes: Vec<MetadataChange>) -> MetadataInfo<Timestamp> {
+        MetadataInfo {
+            changes,
+            inode: MaybeChange::Same(None),
+            created: MaybeChange::Same(None),
+            modified: MaybeChange::Same(None),
+            accessed: MaybeChange::Same(None),
+            inode_modified: MaybeChange::Same(None),
+        }
+    }
+
+    /// Returns a file content change between the hashes filled with the given bytes.
+    pub(crate) fn file_changed(from: u8, to: u8) -> EntryDiff {
+        EntryDiff::file_changed(Hash([from; 32]), Hash([to; 32]))
+    }
+
+    /// Returns the timestamp `secs` seconds after the unix epoch.
+    pub(crate) fn ts(secs: i64) -> Timestamp {
+        time::OffsetDateTime::from_unix_timestamp(secs)
+            .unwrap()
+            .into()
+    }
+
+    /// Returns a changeset with the given entries and the unix epoch as earliest timestamp.
+    pub(crate) fn changeset(
+        entries: Vec<(&str, MetaEntryDiff<Timestamp>)>,
+    ) -> Changeset<Timestamp> {
+        Changeset {
+            schema_version: Changeset::SCHEMA_VERSION,
+            earliest_timestamp: ts(0),
+            changes: entries
+                .into_iter()
+                .map(|(path, diff)| (path.to_string(), diff))
+                .collect(),
+            annotations: Default::default(),
+            risk_scores: Default::default(),
+        }
+    }
+
+    /// Returns metadata whose modified timestamp is `secs` seconds after the unix epoch.
+    pub(crate) fn modified_at(secs: i64) -> MetadataInfo<Timestamp> {
+        let mut info = info(vec![]);
+        info.modified = MaybeChange::Same(Some(ts(secs)));
+        info
+    }
+
+    /// Returns a change of the access control list to the given data.
+    pub(crate) fn acl_change(data: &[u8]) -> MetadataChange {
+        MetadataChange::NamedStream(
+            NamedStreamType::AccessControlList,
+            Change {
+                from: None,
+                to: Some(data.into()),
+            },
+        )
+    }
+
+    /// Returns a change of the alternate data stream with the given name.
+    pub(crate) fn ads_change(name: &str, from: Option<&[u8]>, to: Option<&[u8]>) -> MetadataChange {
+        MetadataChange::NamedStream(
+            NamedStreamType::AlternateDataStream { name: name.into() },
+            Change {
+                from: from.map(Into::into),
+                to: to.map(Into::into),
+            },
+        )
+    }
+
+    #[test]
+    fn operation_mapping() {
+        let cases = [
+            (MetaEntryDiff::Added(info(vec![])), Operation::Create),
+            (MetaEntryDiff::Deleted(info(vec![])), Operation::Delete),
+            (
+                MetaEntryDiff::MetaOnlyChange(info(vec![])),
+                Operation::ModifyMetadata,
+            ),
+            (
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+                Operation::ModifyContent,
+            ),
+            (
+                MetaEntryDiff::EntryChange(
+                    EntryDiff::symlink_changed("a".into(), "b".into()),
+                    info(vec![]),
+                ),
+                Operation::ModifyContent,
+            ),
+            (
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+                Operation::ModifyContent,
+            ),
+            (
+                MetaEntryDiff::EntryChange(
+                    EntryDiff::type_change("file".into(), "directory".into()),
+                    info(vec![]),
+                ),
+                Operation::ChangeType,
+            ),
+        ];
+        for (diff, operation) in cases {
+            assert_eq!(diff.operation(), operation);
+            assert_eq!(Operation::try_from(u8::from(operation)), Ok(operation));
+        }
+        assert!(Operation::try_from(5).is_err());
+    }
+
+    #[test]
+    fn permission_audit_skips_irrelevant_changes() {
+        let acl: Arc<[u8]> = vec![1, 2].into();
+        let mut meta = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::unix_permissions(Some(0o644), Some(0o600)),
+            MetadataChange::nlink(Some(1), Some(2)),
+            MetadataChange::NamedStream(
+                NamedStreamType::AccessControlList,
+                Change {
+                    from: None,
+                    to: Some(acl.clone()),
+                },
+            ),
+            MetadataChange::NamedStream(
+                NamedStreamType::ExtendedAttributes,
+                Change {
+                    from: None,
+                    to: Some(vec![3].into()),
+                },
+            ),
+        ]);
+        meta.inode_modified = MaybeChange::Same(Some(ts(5)));
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(meta)),
+            (
+                "/b",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::gid(Some(0), Some(1))])),
+            ),
+            (
+                "/c",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            ),
+        ]);
+
+        let events = cs.permission_audit();
+        assert_eq!(
+            events,
+            vec![
+                PermissionEvent {
+                    path: "/a".to_string(),
+                    field: PermissionField::UnixPermissions,
+                    change: Change {
+                        from: Some(PermissionValue::Number(0o644)),
+                        to: Some(PermissionValue::Number(0o600)),
+                    },
+                    timestamp: Some(ts(5)),
+                },
+                PermissionEvent {
+                    path: "/a".to_string(),
+                    field: PermissionField::AccessControlList,
+                    change: Change {
+                        from: None,
+                        to: Some(PermissionValue::Bytes(acl)),
+                    },
+                    timestamp: Some(ts(5)),
+                },
+                PermissionEvent {
+                    path: "/b".to_string(),
+                    field: PermissionField::Gid,
+                    change: Change {
+                        from: Some(PermissionValue::Number(0)),
+                        to: Some(PermissionValue::Number(1)),
+                    },
+                    timestamp: None,
+                },
+            ]
+        );
+
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<PermissionEvent<Timestamp>>>(&json).unwrap(),
+            events
+        );
+    }
+
+    #[test]
+    fn diff_of_infos_differing_in_size() {
+        let mut old = info(vec![MetadataChange::size(10, 20)]);
+        old.modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let mut new = info(vec![MetadataChange::size(10, 30)]);
+        new.modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+
+        let mut expected = info(vec![MetadataChange::size(20, 30)]);
+        expected.modified = MaybeChange::Same(Some(ts(2)));
+        assert_eq!(MetadataInfo::diff(&old, &new), expected);
+        assert_eq!(MetadataInfo::diff(&old, &old).changes, vec![]);
+        assert_eq!(
+            MetadataInfo::diff(&old, &info(vec![])).changes,
+            vec![MetadataChange::size(20, 10)]
+        );
+    }
+
+    #[test]
+    fn interning_shares_repeated_streams() {
+        let paths: Vec<_> = (0..100).map(|i| format!("/dir/{i}")).collect();
+        let mut cs = changeset(
+            paths
+                .iter()
+                .map(|path| {
+                    (
+                        path.as_str(),
+                        MetaEntryDiff::MetaOnlyChange(info(vec![acl_change(&[7; 4096])])),
+                    )
+                })
+                .collect(),
+        );
+        cs.changes.insert(
+            "/other".into(),
+            MetaEntryDiff::Added(info(vec![acl_change(&[1])])),
+        );
+        let stored_bytes = |cs: &Changeset<Timestamp>| {
+            let mut seen = std::collections::HashSet::new();
+            cs.changes
+                .values()
+                .flat_map(|diff| &diff.meta_info().changes)
+                .filter_map(|change| match change {
+                    MetadataChange::NamedStream(_, Change { to: Some(data), .. }) => Some(data),
+                    _ => None,
+                })
+                .filter(|data| seen.insert(Arc::as_ptr(data)))
+                .map(|data| data.len())
+                .sum::<usize>()
+        };
+        assert_eq!(stored_bytes(&cs), 100 * 4096 + 1);
+
+        let json = serde_json::to_string(&cs).unwrap();
+        cs.intern_streams();
+        assert_eq!(stored_bytes(&cs), 4096 + 1);
+        assert_eq!(serde_json::to_string(&cs).unwrap(), json);
+        assert_eq!(Changeset::from_json(&json).unwrap(), cs);
+    }
+
+    #[test]
+    fn added_executables_by_extension_or_mode() {
+        let cs = changeset(vec![
+            ("C:\\setup.EXE", MetaEntryDiff::Added(info(vec![]))),
+            ("/notes.txt", MetaEntryDiff::Added(info(vec![]))),
+            (
+                "/usr/bin/tool",
+                MetaEntryDiff::Added(info(vec![MetadataChange::unix_permissions(
+                    None,
+                    Some(0o755),
+                )])),
+            ),
+            ("/deleted.exe", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+
+        let executables: Vec<_> = cs
+            .added_executables(DEFAULT_EXECUTABLE_EXTENSIONS)
+            .collect();
+        assert_eq!(executables, ["/usr/bin/tool", "C:\\setup.EXE"]);
+        let executables: Vec<_> = cs.added_executables(&["txt"]).collect();
+        assert_eq!(executables, ["/notes.txt", "/usr/bin/tool"]);
+    }
+
+    #[test]
+    fn unknown_metadata_changes_are_preserved() {
+        let json = r#"[{"Size":{"from":1,"to":2}},{"Foo":{"a":[1,2]}}]"#;
+        let changes: Vec<MetadataChange> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                MetadataChange::size(1, 2),
+                MetadataChange::Unknown(UnknownMetadataChange {
+                    tag: "Foo".to_string(),
+                    value: serde_json::json!({"a": [1, 2]}),
+                }),
+            ]
+        );
+        assert_eq!(serde_json::to_string(&changes).unwrap(), json);
+
+        let json =
+            r#"{"NamedStream":[{"AlternateDataStream":{"name":"x"}},{"from":null,"to":[1]}]}"#;
+        let change: MetadataChange = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            change,
+            MetadataChange::NamedStream(
+                NamedStreamType::AlternateDataStream {
+                    name: "x".to_string()
+                },
+                Change {
+                    from: None,
+                    to: Some(vec![1].into()),
+                },
+            )
+        );
+        assert_eq!(serde_json::to_string(&change).unwrap(), json);
+
+        for invalid in [
+            r#"{"Size":{"from":"x","to":2}}"#,
+            r#"{"Size":{"from":1,"to":2},"Uid":{"from":1,"to":2}}"#,
+            r#"{}"#,
+            r#"[1]"#,
+        ] {
+            assert!(serde_json::from_str::<MetadataChange>(invalid).is_err());
+        }
+
+        let mut cs = changeset(vec![(
+            "/a",
+            MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+        )]);
+        let mut value = cs.to_json_value().unwrap();
+        value["changes"]["/a"]["MetaOnlyChange"]["changes"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({"Foo": 3}));
+        cs = Changeset::from_json_value(value).unwrap();
+        assert_eq!(
+            cs.changes["/a"].meta_info().changes[1],
+            MetadataChange::Unknown(UnknownMetadataChange {
+                tag: "Foo".to_string(),
+                value: 3.into(),
+            })
+        );
+    }
+
+    #[test]
+    fn change_max_and_min() {
+        let growing = Change { from: 1u64, to: 5 };
+        assert_eq!((*growing.max(), *growing.min()), (5, 1));
+        let shrinking = Change { from: 9u64, to: 5 };
+        assert_eq!((*shrinking.max(), *shrinking.min()), (9, 5));
+    }
+
+    #[test]
+    fn since_and_until_a_mid_range_cutoff() {
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(modified_at(10))),
+            ("/b", MetaEntryDiff::MetaOnlyChange(modified_at(20))),
+            ("/c", MetaEntryDiff::MetaOnlyChange(modified_at(30))),
+            ("/d", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+
+        let since = cs.since(&ts(20));
+        assert_eq!(since.changes.keys().collect::<Vec<_>>(), ["/b", "/c"]);
+        assert_eq!(since.earliest_timestamp, ts(20));
+        assert_eq!(cs.since(&ts(15)).earliest_timestamp, ts(20));
+        let until = cs.until(&ts(20));
+        assert_eq!(until.changes.keys().collect::<Vec<_>>(), ["/a", "/b"]);
+        assert_eq!(until.earliest_timestamp, ts(10));
+        assert_eq!(cs.until(&ts(5)).earliest_timestamp, ts(0));
+    }
+
+    #[test]
+    fn format_size_unit_boundaries() {
+        const KIB: u64 = 1 << 10;
+        const MIB: u64 = 1 << 20;
+        const GIB: u64 = 1 << 30;
+        const TIB: u64 = 1 << 40;
+
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(KIB - 1), "1023 B");
+        assert_eq!(format_size(KIB), "1.00 KiB");
+        assert_eq!(format_size(MIB - 1), "1.00 MiB");
+        assert_eq!(format_size(1048570), "1023.99 KiB");
+        assert_eq!(format_size(1048571), "1.00 MiB");
+        assert_eq!(format_size(MIB), "1.00 MiB");
+        assert_eq!(format_size(GIB - 1), "1.00 GiB");
+        assert_eq!(format_size(GIB), "1.00 GiB");
+        assert_eq!(format_size(TIB - 1), "1.00 TiB");
+        assert_eq!(format_size(TIB), "1.00 TiB");
+        assert_eq!(format_size(1536 * GIB), "1.50 TiB");
+        assert_eq!(format_size(u64::MAX), "16.00 EiB");
+    }
+
+    #[test]
+    fn describe_size_with_delta() {
+        assert_eq!(
+            MetadataChange::size(4398046511104, 4402341478400)
+                .describe_size()
+                .unwrap(),
+            "4.00 TiB -> 4.00 TiB (+4.00 GiB)"
+        );
+        assert_eq!(
+            MetadataChange::size(2048, 1024).describe_size().unwrap(),
+            "2.00 KiB -> 1.00 KiB (-1.00 KiB)"
+        );
+        assert_eq!(MetadataChange::uid(None, Some(1)).describe_size(), None);
+    }
+
+    #[test]
+    fn merge_changes_clean_and_conflicting() {
+        let uid = MetadataChange::uid(None, Some(1));
+        assert_eq!(
+            merge_changes(
+                &[MetadataChange::size(10, 20)],
+                &[MetadataChange::size(10, 20), uid.clone()]
+            ),
+            Ok(vec![MetadataChange::size(10, 20), uid.clone()])
+        );
+
+        assert_eq!(
+            merge_changes(
+                &[MetadataChange::size(10, 20), uid.clone()],
+                &[MetadataChange::size(10, 30), uid]
+            ),
+            Err(vec![ChangeConflict {
+                first: MetadataChange::size(10, 20),
+                second: MetadataChange::size(10, 30),
+            }])
+        );
+    }
+
+    #[test]
+    fn get_and_contains_path() {
+        let cs = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        assert_eq!(cs.get("/a"), Some(&MetaEntryDiff::Added(info(vec![]))));
+        assert_eq!(cs.get("/b"), None);
+        assert!(cs.contains_path("/a"));
+        assert!(!cs.contains_path("/b"));
+    }
+
+    #[test]
+    fn sorted_vecs_match_map_order() {
+        let cs = changeset(vec![
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/a/c", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/a", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+        let borrowed: Vec<_> = cs
+            .to_sorted_vec()
+            .into_iter()
+            .map(|(path, _)| path.clone())
+            .collect();
+        assert_eq!(borrowed, ["/a", "/a/c", "/b"]);
+        assert_eq!(
+            cs.clone().into_sorted_vec(),
+            cs.changes.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn truncate_nanoseconds() {
+        let timestamp: Timestamp =
+            time::macros::datetime!(2023-01-02 03:04:05.123456789 UTC).into();
+        let truncated = |precision| timestamp.truncate_to(precision);
+        assert_eq!(
+            truncated(TimePrecision::Seconds),
+            time::macros::datetime!(2023-01-02 03:04:05 UTC).into()
+        );
+        assert_eq!(
+            truncated(TimePrecision::Millis),
+            time::macros::datetime!(2023-01-02 03:04:05.123 UTC).into()
+        );
+        assert_eq!(
+            truncated(TimePrecision::Micros),
+            time::macros::datetime!(2023-01-02 03:04:05.123456 UTC).into()
+        );
+        assert_eq!(truncated(TimePrecision::Nanos), timestamp);
+
+        let mut meta = info(vec![]);
+        meta.accessed = MaybeChange::changed(None, Some(timestamp.clone()));
+        let mut cs = changeset(vec![("/a", MetaEntryDiff::MetaOnlyChange(meta))]);
+        cs.earliest_timestamp = timestamp;
+        cs.truncate_timestamps(TimePrecision::Seconds);
+        let seconds: Timestamp = time::macros::datetime!(2023-01-02 03:04:05 UTC).into();
+        assert_eq!(
+            cs.changes["/a"].meta_info().accessed,
+            MaybeChange::changed(None, Some(seconds.clone()))
+        );
+        assert_eq!(cs.earliest_timestamp, seconds);
+    }
+
+    #[test]
+    fn acl_only_changes() {
+        let mut with_timestamp = info(vec![acl_change(&[1])]);
+        with_timestamp.inode_modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let cs = changeset(vec![
+            (
+                "/acl",
+                MetaEntryDiff::MetaOnlyChange(info(vec![acl_change(&[1])])),
+            ),
+            (
+                "/acl_and_timestamp",
+                MetaEntryDiff::MetaOnlyChange(with_timestamp),
+            ),
+            (
+                "/mixed",
+                MetaEntryDiff::MetaOnlyChange(info(vec![
+                    acl_change(&[1]),
+                    MetadataChange::size(1, 2),
+                ])),
+            ),
+            ("/nothing", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            (
+                "/content",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![acl_change(&[1])])),
+            ),
+        ]);
+        assert_eq!(
+            cs.acl_only_changes().collect::<Vec<_>>(),
+            ["/acl", "/acl_and_timestamp"]
+        );
+    }
+
+    #[test]
+    fn read_index_skips_metadata() {
+        let mut cs = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![acl_change(&[1; 64])])),
+            ),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/c", MetaEntryDiff::Added(modified_at(5))),
+            ("/d", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+        cs.earliest_timestamp = ts(3);
+        let mut value = cs.to_json_value().unwrap();
+        value["changes"]["/d"]["MetaOnlyChange"] = serde_json::json!("not metadata");
+
+        let index = read_index(value.to_string().as_bytes()).unwrap();
+        assert_eq!(index.earliest_timestamp, ts(3));
+        assert_eq!(
+            index.changes.into_iter().collect::<Vec<_>>(),
+            [
+                ("/a".to_string(), MetaEntryDiffKind::EntryChange),
+                ("/b".to_string(), MetaEntryDiffKind::Deleted),
+                ("/c".to_string(), MetaEntryDiffKind::Added),
+                ("/d".to_string(), MetaEntryDiffKind::MetaOnlyChange),
+            ]
+        );
+
+        value.as_object_mut().unwrap().remove("schema_version");
+        assert!(read_index(value.to_string().as_bytes()).is_ok());
+
+        let future_version = Changeset::SCHEMA_VERSION + 1;
+        value["schema_version"] = future_version.into();
+        let err = read_index(value.to_string().as_bytes()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("unsupported schema version {future_version}")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn len_and_is_empty() {
+        let empty = changeset(vec![]);
+        assert_eq!(empty.len(), 0);
+        assert!(empty.is_empty());
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+        assert_eq!(cs.len(), 2);
+        assert!(!cs.is_empty());
+    }
+
+    #[test]
+    fn map_paths_case_folding() {
+        let link = MetaEntryDiff::EntryChange(
+            EntryDiff::symlink_changed("/X".into(), "/Y".into()),
+            info(vec![]),
+        );
+        let mut cs = changeset(vec![("/Link", link)]);
+        cs.annotate("/Link", serde_json::json!("note"));
+        let folded = cs.map_paths(|path| path.to_lowercase()).unwrap();
+        assert_eq!(
+            folded.get("/link"),
+            Some(&MetaEntryDiff::EntryChange(
+                EntryDiff::symlink_changed("/x".into(), "/y".into()),
+                info(vec![]),
+            ))
+        );
+        assert_eq!(folded.annotation("/link"), Some(&serde_json::json!("note")));
+
+        cs.changes
+            .insert("/link".into(), MetaEntryDiff::Added(info(vec![])));
+        assert_eq!(
+            cs.map_paths(|path| path.to_lowercase()),
+            Err("paths `/Link` and `/link` are both mapped to `/link`".to_string())
+        );
+    }
+
+    #[test]
+    fn map_paths_annotation_collision() {
+        let mut cs = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        cs.annotate("/Note", serde_json::json!(1));
+        cs.annotate("/note", serde_json::json!(2));
+        assert_eq!(
+            cs.map_paths(|path| path.to_lowercase()),
+            Err("paths `/Note` and `/note` are both mapped to `/note`".to_string())
+        );
+
+        let mut calls = Vec::new();
+        let mut cs = changeset(vec![(
+            "/link",
+            MetaEntryDiff::EntryChange(
+                EntryDiff::symlink_changed("/a".into(), "/link".into()),
+                info(vec![]),
+            ),
+        )]);
+        cs.annotate("/link", serde_json::json!(1));
+        cs.risk_scores.insert("/link".into(), 50);
+        let mapped = cs
+            .map_paths(|path| {
+                calls.push(path.to_string());
+                path.to_uppercase()
+            })
+            .unwrap();
+        assert_eq!(calls, ["/link", "/a"]);
+        assert_eq!(mapped.annotation("/LINK"), Some(&serde_json::json!(1)));
+        assert_eq!(mapped.risk_score("/LINK"), Some(50));
+    }
+
+    #[test]
+    fn backdated_modified_timestamp() {
+        let mut meta = info(vec![]);
+        meta.modified = MaybeChange::changed(Some(ts(100)), Some(ts(50)));
+        meta.accessed = MaybeChange::changed(Some(ts(100)), None);
+        meta.created = MaybeChange::changed(Some(ts(100)), Some(ts(150)));
+        assert_eq!(meta.backdated_timestamps(), [TimestampKind::Modified]);
+
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(meta)),
+            ("/b", MetaEntryDiff::MetaOnlyChange(modified_at(3))),
+        ]);
+        assert_eq!(
+            cs.backdated_entries().collect::<Vec<_>>(),
+            [(&"/a".to_string(), vec![TimestampKind::Modified])]
+        );
+    }
+
+    #[test]
+    fn pretty_json_round_trip() {
+        let mut meta = info(vec![MetadataChange::size(1, 2)]);
+        meta.modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::EntryChange(file_changed(1, 2), meta)),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+        let json = cs.to_pretty_json().unwrap();
+        assert!(json.contains(&format!("\"{}\"", "01".repeat(32))), "{json}");
+        assert!(json.contains("\"1970-01-01 00:00:02.0\""), "{json}");
+        assert_eq!(Changeset::from_json(&json).unwrap(), cs);
+    }
+
+    #[test]
+    fn access_only_changes() {
+        let mut accessed = info(vec![]);
+        accessed.accessed = MaybeChange::changed(Some(ts(100)), Some(ts(150)));
+        let mut with_inode_modified = accessed.clone();
+        with_inode_modified.inode_modified = MaybeChange::changed(Some(ts(100)), Some(ts(150)));
+        let mut with_size = accessed.clone();
+        with_size.changes.push(MetadataChange::size(1, 2));
+
+        let cs = changeset(vec![
+            ("/accessed", MetaEntryDiff::MetaOnlyChange(accessed.clone())),
+            (
+                "/changed",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), accessed),
+            ),
+            (
+                "/inode_modified",
+                MetaEntryDiff::MetaOnlyChange(with_inode_modified),
+            ),
+            ("/size", MetaEntryDiff::MetaOnlyChange(with_size)),
+            ("/unchanged", MetaEntryDiff::MetaOnlyChange(modified_at(3))),
+        ]);
+        assert_eq!(cs.accessed_only().collect::<Vec<_>>(), ["/accessed"]);
+    }
+
+    #[test]
+    fn optional_change_transitions() {
+        let change = |from, to| Change::<Option<u32>> { from, to };
+
+        let added = change(None, Some(1));
+        assert!(added.is_added() && !added.is_removed() && !added.is_modified());
+        let removed = change(Some(1), None);
+        assert!(!removed.is_added() && removed.is_removed() && !removed.is_modified());
+        let modified = change(Some(1), Some(2));
+        assert!(!modified.is_added() && !modified.is_removed() && modified.is_modified());
+        let same = change(Some(1), Some(1));
+        assert!(!same.is_added() && !same.is_removed() && !same.is_modified());
+    }
+
+    #[test]
+    fn annotations_round_trip() {
+        let mut cs = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        let json = cs.to_pretty_json().unwrap();
+        assert!(!json.contains("annotations"), "{json}");
+        let mut legacy = cs.to_json_value().unwrap();
+        legacy.as_object_mut().unwrap().remove("annotations");
+        assert_eq!(Changeset::from_json_value(legacy).unwrap(), cs);
+
+        assert_eq!(
+            cs.annotate("/a", serde_json::json!({"verdict": "bad"})),
+            None
+        );
+        assert_eq!(
+            cs.annotate("/a", serde_json::json!({"verdict": "benign"})),
+            Some(serde_json::json!({"verdict": "bad"}))
+        );
+        let back = Changeset::from_json(&cs.to_pretty_json().unwrap()).unwrap();
+        assert_eq!(back.annotation("/a").unwrap()["verdict"], "benign");
+        assert_eq!(back.annotation("/b"), None);
+        assert_eq!(back, cs);
+    }
+
+    #[test]
+    fn fim_alert_for_content_change() {
+        let mut meta = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::nlink(Some(1), Some(2)),
+        ]);
+        meta.modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::EntryChange(file_changed(1, 2), meta)),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+
+        let alerts = cs.to_fim_alerts();
+        assert_eq!(
+            alerts[0],
+            FimAlert {
+                path: "/a".to_string(),
+                event: FimEvent::Modified,
+                changed_attributes: vec!["size".into(), "mtime".into(), "sha256".into()],
+                old_sha256: Some("01".repeat(32)),
+                new_sha256: Some("02".repeat(32)),
+                timestamp: Some(ts(2)),
+            }
+        );
+        assert_eq!(alerts[1].event, FimEvent::Deleted);
+        assert_eq!(alerts[1].old_sha256, None);
+    }
+
+    #[test]
+    fn eq_ignoring_timestamps() {
+        let entry = |modified, hash| {
+            let mut meta = info(vec![MetadataChange::size(1, 2)]);
+            meta.modified = MaybeChange::changed(Some(ts(1)), Some(ts(modified)));
+            MetaEntryDiff::EntryChange(file_changed(1, hash), meta)
+        };
+        let a = changeset(vec![("/a", entry(3, 2))]);
+        let mut b = changeset(vec![("/a", entry(5, 2))]);
+        b.earliest_timestamp = ts(99);
+        assert_ne!(a, b);
+        assert!(a.eq_ignoring_timestamps(&b));
+
+        let c = changeset(vec![("/a", entry(3, 3))]);
+        assert!(!a.eq_ignoring_timestamps(&c));
+        let d = changeset(vec![("/b", entry(3, 2))]);
+        assert!(!a.eq_ignoring_timestamps(&d));
+    }
+
+    #[test]
+    fn most_recent_entry() {
+        let mut created = info(vec![]);
+        created.created = MaybeChange::Same(Some(ts(7)));
+        let cs = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(modified_at(3))),
+            ("/b", MetaEntryDiff::MetaOnlyChange(modified_at(9))),
+            ("/c", MetaEntryDiff::MetaOnlyChange(modified_at(5))),
+            ("/d", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        assert_eq!(cs.most_recent().unwrap().0, "/b");
+
+        let mut tied = cs.clone();
+        tied.changes
+            .insert("/e".into(), MetaEntryDiff::MetaOnlyChange(modified_at(9)));
+        assert_eq!(tied.most_recent().unwrap().0, "/b");
+        let fallback = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(modified_at(3))),
+            ("/b", MetaEntryDiff::Added(created)),
+        ]);
+        assert_eq!(fallback.most_recent().unwrap().0, "/b");
+        assert_eq!(changeset(vec![]).most_recent(), None);
+    }
+
+    #[test]
+    fn fixed_subsecond_precision() {
+        let timestamp: Timestamp = time::macros::datetime!(2023-01-02 03:04:05.1 UTC).into();
+        assert_eq!(timestamp.format_with_precision(0), "2023-01-02 03:04:05");
+        assert_eq!(
+            timestamp.format_with_precision(3),
+            "2023-01-02 03:04:05.100"
+        );
+        assert_eq!(
+            timestamp.format_with_precision(6),
+            "2023-01-02 03:04:05.100000"
+        );
+        assert_eq!(
+            timestamp.format_with_precision(9),
+            "2023-01-02 03:04:05.100000000"
+        );
+        assert_eq!(
+            serde_json::to_string(&timestamp).unwrap(),
+            "\"2023-01-02 03:04:05.1\""
+        );
+
+        let fixed = FixedPrecisionTimestamp::<3>(timestamp);
+        let json = serde_json::to_string(&fixed).unwrap();
+        assert_eq!(json, "\"2023-01-02 03:04:05.100\"");
+        assert_eq!(
+            serde_json::from_str::<FixedPrecisionTimestamp<3>>(&json).unwrap(),
+            fixed
+        );
+        let fixed = FixedPrecisionTimestamp::<9>(fixed.0);
+        assert_eq!(
+            serde_json::to_string(&fixed).unwrap(),
+            "\"2023-01-02 03:04:05.100000000\""
+        );
+    }
+
+    #[test]
+    fn from_system_time() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let system_time = UNIX_EPOCH + Duration::new(1_700_000_000, 5);
+        let expected: Timestamp =
+            time::OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_000_000_005)
+                .unwrap()
+                .into();
+        assert_eq!(Timestamp::from(system_time), expected);
+        assert_eq!(Timestamp::try_from_system_time(system_time), Ok(expected));
+        assert_eq!(
+            Timestamp::try_from_system_time(UNIX_EPOCH - Duration::from_secs(10)),
+            Ok(ts(-10))
+        );
+        assert!(
+            Timestamp::try_from_system_time(UNIX_EPOCH + Duration::from_secs(1 << 40)).is_err()
+        );
+    }
+
+    #[test]
+    fn touched_paths_of_overlapping_changesets() {
+        let sets = [
+            changeset(vec![
+                ("/a", MetaEntryDiff::Added(info(vec![]))),
+                ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ]),
+            changeset(vec![
+                ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+                ("/c", MetaEntryDiff::Added(info(vec![]))),
+            ]),
+        ];
+        assert_eq!(
+            touched_paths(&sets).into_iter().collect::<Vec<_>>(),
+            ["/a", "/b", "/c"]
+        );
+        assert!(touched_paths::<Timestamp>(&[]).is_empty());
+    }
+
+    #[test]
+    fn directories_with_many_additions() {
+        let mut cs = changeset(vec![
+            ("/drop/e", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/few/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/few/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/many/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/many/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/many/c", MetaEntryDiff::Added(info(vec![]))),
+            ("/some/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/some/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/some/c", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        for name in ["d", "e"] {
+            cs.changes
+                .insert(format!("/many/{name}"), MetaEntryDiff::Added(info(vec![])));
+        }
+        cs.changes
+            .insert("/some/d".into(), MetaEntryDiff::Deleted(info(vec![])));
+
+        assert_eq!(
+            cs.directories_with_many_additions(3),
+            [("/many".to_string(), 5), ("/some".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    fn empty_changes_are_skipped() {
+        let info = info(vec![]);
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(!json.contains("changes"), "{json}");
+        assert_eq!(
+            serde_json::from_str::<MetadataInfo<Timestamp>>(&json).unwrap(),
+            info
+        );
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("changes".into(), serde_json::json!([]));
+        assert_eq!(
+            serde_json::from_value::<MetadataInfo<Timestamp>>(value).unwrap(),
+            info
+        );
+    }
+
+    #[test]
+    fn ntfs_attribute_diff() {
+        let change = MetadataChange::NtfsAttributes(Change {
+            from: Some((NtfsAttributes::READONLY | NtfsAttributes::ARCHIVE).bits()),
+            to: Some((NtfsAttributes::HIDDEN | NtfsAttributes::ARCHIVE).bits()),
+        });
+        assert_eq!(
+            change.ntfs_attribute_diff(),
+            Some((NtfsAttributes::HIDDEN, NtfsAttributes::READONLY))
+        );
+        assert_eq!(MetadataChange::size(1, 2).ntfs_attribute_diff(), None);
+    }
+
+    #[test]
+    fn unix_mode_diff() {
+        let change = MetadataChange::UnixPermissions(Change {
+            from: Some(0o100644),
+            to: Some(0o104755),
+        });
+        let diff = change.unix_mode_diff().unwrap();
+        assert!(diff.sets_privileged_bits());
+        assert_eq!(
+            diff.set,
+            UnixMode::SETUID
+                | UnixMode::USER_EXECUTE
+                | UnixMode::GROUP_EXECUTE
+                | UnixMode::OTHER_EXECUTE
+        );
+        assert!(diff.cleared.is_empty());
+    }
+
+    #[test]
+    fn json_value_round_trip() {
+        let mut changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 2)])),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+            ),
+        ]);
+        changeset.annotate("/a", serde_json::json!({ "ticket": 1 }));
+
+        let value = changeset.to_json_value().unwrap();
+        let pretty: serde_json::Value =
+            serde_json::from_str(&changeset.to_pretty_json().unwrap()).unwrap();
+        assert_eq!(value, pretty);
+        assert_eq!(Changeset::from_json_value(value).unwrap(), changeset);
+    }
+
+    #[test]
+    fn semantic_eq_ignores_change_order() {
+        let uid = MetadataChange::Uid(Change {
+            from: Some(1),
+            to: Some(2),
+        });
+        let entry = |changes| changeset(vec![("/a", MetaEntryDiff::MetaOnlyChange(info(changes)))]);
+
+        let a = entry(vec![MetadataChange::size(1, 2), uid.clone()]);
+        let b = entry(vec![uid.clone(), MetadataChange::size(1, 2)]);
+        assert_ne!(a, b);
+        assert!(a.semantic_eq(&b));
+        assert!(!a.semantic_eq(&entry(vec![uid.clone(), uid])));
+    }
+
+    #[test]
+    fn newly_created() {
+        let mut created = info(vec![]);
+        created.created = MaybeChange::Same(Some(ts(5)));
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(created.clone())),
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/c", MetaEntryDiff::Deleted(created)),
+        ]);
+
+        assert_eq!(
+            changeset.newly_created().collect::<Vec<_>>(),
+            [(&"/a".to_string(), &ts(5))]
+        );
+    }
+
+    #[test]
+    fn common_paths() {
+        let a = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        let b = changeset(vec![
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/c", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+
+        let common: Vec<_> = a.common_paths(&b).collect();
+        assert_eq!(common.len(), 1);
+        assert_eq!(common[0].0, "/b");
+        assert!(matches!(common[0].1, MetaEntryDiff::Added(_)));
+        assert!(matches!(common[0].2, MetaEntryDiff::Deleted(_)));
+    }
+
+    #[test]
+    fn describe_optional_changes() {
+        let describe = |from, to| describe_optional_change(&Change { from, to });
+        assert_eq!(describe(None, Some(755)), "(unset) -> 755");
+        assert_eq!(describe(Some(644), None), "644 -> (removed)");
+        assert_eq!(describe(Some(644), Some(755)), "644 -> 755");
+        assert_eq!(describe(None, None), "(unset) -> (unset)");
+
+        let uid = MetadataChange::Uid(Change {
+            from: None,
+            to: Some(5),
+        });
+        assert_eq!(uid.describe(), "uid: (unset) -> 5");
+        let permissions = MetadataChange::UnixPermissions(Change {
+            from: Some(0o644),
+            to: None,
+        });
+        assert_eq!(permissions.describe(), "permissions: 644 -> (removed)");
+    }
+
+    #[test]
+    fn dedup_by_content() {
+        let resized = || MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 2)]));
+        let changeset = changeset(vec![
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/m1/a", resized()),
+            ("/m2/a", resized()),
+        ]);
+
+        let groups = changeset.dedup_by_content();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&resized()], ["/m1/a", "/m2/a"]);
+    }
+
+    #[test]
+    fn stats_by_extension() {
+        let changeset = changeset(vec![
+            ("/Makefile", MetaEntryDiff::Added(info(vec![]))),
+            ("/a.TXT", MetaEntryDiff::Added(info(vec![]))),
+            ("/b.txt", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/c.tar.gz", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+
+        let stats = changeset.stats_by_extension();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(
+            stats["txt"],
+            ChangeKindCounts {
+                added: 1,
+                deleted: 1,
+                modified: 0
+            }
+        );
+        assert_eq!(stats["gz"].modified, 1);
+        assert_eq!(stats[""].added, 1);
+    }
+
+    #[test]
+    fn timestamps_round_trip_losslessly() {
+        // A simple linear congruential generator keeps the test deterministic.
+        let mut state: u64 = 0x1234_5678_9abc_def0;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 11
+        };
+
+        for _ in 0..1000 {
+            let secs = (next() % 8_000_000_000) as i64 - 2_000_000_000;
+            let nanos = (next() % 1_000_000_000) as u32;
+            let offset =
+                time::UtcOffset::from_whole_seconds((next() % 50_000) as i32 - 25_000).unwrap();
+            let timestamp = Timestamp::from(
+                time::OffsetDateTime::from_unix_timestamp(secs)
+                    .unwrap()
+                    .replace_nanosecond(nanos)
+                    .unwrap()
+                    .to_offset(offset),
+            );
+
+            let json = serde_json::to_string(&timestamp).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Timestamp>(&json).unwrap(),
+                timestamp,
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn timestamps_outside_of_utc_fail_to_serialize() {
+        let late = time::macros::datetime!(9999-12-31 23:00 -05:00);
+        assert!(Timestamp::try_from_offset_date_time(late).is_err());
+        assert!(serde_json::to_string(&Timestamp::from(late)).is_err());
+        assert!(serde_json::to_string(&FixedPrecisionTimestamp::<3>(late.into())).is_err());
+        assert_eq!(
+            Timestamp::from(late).format_with_precision(0),
+            "9999-12-31 23:00:00 -05:00:00"
+        );
+
+        let early = time::macros::datetime!(9999-12-31 23:00 +05:00);
+        assert_eq!(
+            Timestamp::try_from_offset_date_time(early).unwrap(),
+            Timestamp::from(early)
+        );
+        assert_eq!(
+            serde_json::to_string(&Timestamp::from(early)).unwrap(),
+            "\"9999-12-31 18:00:00.0\""
+        );
+    }
+
+    #[test]
+    fn timestamps_compare_by_instant() {
+        use std::hash::BuildHasher as _;
+
+        let offset = Timestamp::from(time::macros::datetime!(1970-01-01 05:00:01 +05:00));
+        assert_eq!(offset, ts(1));
+        assert_eq!(offset.cmp(&ts(1)), std::cmp::Ordering::Equal);
+        assert!(offset < ts(2));
+        let hasher = std::collections::hash_map::RandomState::new();
+        assert_eq!(hasher.hash_one(&offset), hasher.hash_one(ts(1)));
+        assert_eq!(format!("{offset:?}"), "1970-01-01 00:00:01.0");
+    }
+
+    #[test]
+    fn ownership_changes() {
+        let uid = Change {
+            from: Some(0),
+            to: Some(1000),
+        };
+        let gid = Change {
+            from: Some(0),
+            to: Some(100),
+        };
+        let changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::MetaOnlyChange(info(vec![
+                    MetadataChange::Uid(uid.clone()),
+                    MetadataChange::Gid(gid.clone()),
+                ])),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset.ownership_changes().collect::<Vec<_>>(),
+            [(&"/a".to_string(), Some(&uid), Some(&gid))]
+        );
+    }
+
+    #[test]
+    fn reader_writer_round_trip() {
+        let changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 2)])),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+            ),
+        ]);
+
+        let mut buf = Vec::new();
+        changeset.to_writer(&mut buf).unwrap();
+        assert_eq!(Changeset::from_reader(&buf[..]).unwrap(), changeset);
+    }
+
+    #[test]
+    fn hidden_and_modified() {
+        let hide = MetadataChange::NtfsAttributes(Change {
+            from: Some(NtfsAttributes::ARCHIVE.bits()),
+            to: Some((NtfsAttributes::ARCHIVE | NtfsAttributes::HIDDEN).bits()),
+        });
+
+        assert!(
+            MetaEntryDiff::EntryChange(file_changed(0, 1), info(vec![hide.clone()]))
+                .is_hidden_and_modified()
+        );
+        assert!(!MetaEntryDiff::EntryChange(
+            file_changed(0, 1),
+            info(vec![MetadataChange::size(1, 2)])
+        )
+        .is_hidden_and_modified());
+        assert!(!MetaEntryDiff::MetaOnlyChange(info(vec![hide])).is_hidden_and_modified());
+    }
+
+    #[test]
+    fn into_transform_timestamps() {
+        let mut info = info(vec![MetadataChange::size(1, 2)]);
+        info.modified = MaybeChange::Change(Change {
+            from: Some(ts(1)),
+            to: Some(ts(2)),
+        });
+        let changeset = changeset(vec![("/a", MetaEntryDiff::Added(info))]);
+
+        let expected = changeset.transform_timestamps(|ts| ts.unix_timestamp());
+        assert_eq!(
+            changeset.into_transform_timestamps(|ts| ts.unix_timestamp()),
+            expected
+        );
+    }
+
+    #[test]
+    fn detect_renames() {
+        let with_inode = |inode| {
+            let mut info = info(vec![]);
+            info.inode = MaybeChange::Same(Some(inode));
+            info
+        };
+        let changeset = changeset(vec![
+            ("/h0", MetaEntryDiff::Deleted(with_inode(8))),
+            ("/h1", MetaEntryDiff::Added(with_inode(8))),
+            ("/h2", MetaEntryDiff::Added(with_inode(8))),
+            ("/new", MetaEntryDiff::Added(with_inode(7))),
+            ("/old", MetaEntryDiff::Deleted(with_inode(7))),
+            ("/x", MetaEntryDiff::Added(with_inode(9))),
+        ]);
+
+        assert_eq!(
+            changeset.detect_renames(),
+            [("/old".to_string(), "/new".to_string())]
+        );
+    }
+
+    #[test]
+    fn subset() {
+        let mut changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/c", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        changeset.annotate("/a", serde_json::json!(1));
+        changeset.annotate("/c", serde_json::json!(2));
+
+        let subset = changeset.subset(["/a", "/b", "/missing"]);
+        assert_eq!(subset.changes.keys().collect::<Vec<_>>(), ["/a", "/b"]);
+        assert_eq!(subset.annotations.len(), 1);
+        assert_eq!(subset.earliest_timestamp, changeset.earliest_timestamp);
+    }
+
+    #[test]
+    fn meta_entry_diffs_are_ordered_by_kind() {
+        let set: std::collections::BTreeSet<_> = [
+            MetaEntryDiff::EntryChange(file_changed(0, 1), info(vec![])),
+            MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            MetaEntryDiff::Deleted(info(vec![])),
+            MetaEntryDiff::Added(info(vec![MetadataChange::size(2, 3)])),
+            MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 5)])),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            set.iter().map(MetaEntryDiff::kind).collect::<Vec<_>>(),
+            [
+                MetaEntryDiffKind::Added,
+                MetaEntryDiffKind::Added,
+                MetaEntryDiffKind::Deleted,
+                MetaEntryDiffKind::MetaOnlyChange,
+                MetaEntryDiffKind::EntryChange,
+            ]
+        );
+        assert_eq!(
+            set.first(),
+            Some(&MetaEntryDiff::Added(info(vec![MetadataChange::size(
+                1, 5
+            )])))
+        );
+    }
+
+    #[test]
+    fn to_dot() {
+        let changeset = changeset(vec![
+            ("/dir/a\"b", MetaEntryDiff::Added(info(vec![]))),
+            ("C:\\dir\\y", MetaEntryDiff::Deleted(info(vec![]))),
+            ("dir/z", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+
+        let dot = changeset.to_dot();
+        assert_eq!(dot.lines().filter(|l| l.contains("[label=")).count(), 8);
+        assert_eq!(dot.lines().filter(|l| l.contains("->")).count(), 5);
+        assert!(dot.contains(r#""/" [label="/", shape=folder];"#), "{dot}");
+        assert!(dot.contains(r#""/" -> "/dir";"#), "{dot}");
+        assert!(
+            dot.contains(r#""/dir/a\"b" [label="a\"b", color=green];"#),
+            "{dot}"
+        );
+        assert!(dot.contains(r#""C:" -> "C:/dir";"#), "{dot}");
+        assert!(dot.contains(r#""dir" -> "dir/z";"#), "{dot}");
+    }
+
+    #[test]
+    fn hash_bytes() {
+        let mut bytes = [0; Hash::LEN];
+        bytes[0] = 7;
+        let hash = Hash(bytes);
+        assert_eq!(hash.as_bytes(), &bytes);
+        assert_eq!(hash.into_bytes(), bytes);
+    }
+
+    #[test]
+    fn hash_from_slice() {
+        let bytes = [3; 32];
+        assert_eq!(Hash::try_from(&bytes[..]), Ok(Hash([3; 32])));
+        assert_eq!(
+            Hash::try_from(&bytes[..31]),
+            Err("expected 32 bytes, got 31".to_string())
+        );
+        assert_eq!(Hash::from([1; 32]), Hash([1; 32]));
+    }
+
+    #[test]
+    fn timestamp_histogram() {
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(modified_at(5))),
+            ("/b", MetaEntryDiff::MetaOnlyChange(modified_at(5))),
+            ("/c", MetaEntryDiff::MetaOnlyChange(modified_at(5))),
+            ("/d", MetaEntryDiff::MetaOnlyChange(modified_at(9))),
+            ("/e", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+
+        assert_eq!(
+            changeset
+                .timestamp_histogram()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [(ts(5), 3), (ts(9), 1)]
+        );
+    }
+
+    #[test]
+    fn strict_parsing_rejects_unknown_fields() {
+        let changeset = changeset(vec![(
+            "/a",
+            MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 2)])),
+        )]);
+        let json = changeset.to_pretty_json().unwrap();
+        assert_eq!(Changeset::from_json_strict(&json).unwrap(), changeset);
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["changes"]["/a"]["Added"]
+            .as_object_mut()
+            .unwrap()
+            .insert("extra".into(), serde_json::json!(1));
+        let extra = value.to_string();
+        assert_eq!(Changeset::from_json(&extra).unwrap(), changeset);
+        let err = Changeset::<Timestamp>::from_json_strict(&extra)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("extra"), "{err}");
+
+        value["changes"]["/a"]["Added"]["changes"] = serde_json::json!([{ "Future": 1 }]);
+        assert!(Changeset::<Timestamp>::from_json(&value.to_string()).is_ok());
+        assert!(Changeset::<Timestamp>::from_json_strict(&value.to_string()).is_err());
+        assert!(Changeset::<Timestamp>::from_json_strict(&format!("{json} x")).is_err());
+    }
+
+    #[test]
+    fn common_root() {
+        let common_root = |paths: &[&str]| {
+            let entries = paths
+                .iter()
+                .map(|path| (*path, MetaEntryDiff::Added(info(vec![]))))
+                .collect();
+            changeset(entries).common_root()
+        };
+
+        assert_eq!(
+            common_root(&["/home/user/a", "/home/user/b/c", "/home/user2"]),
+            Some("/home".into())
+        );
+        assert_eq!(common_root(&["/home/ab", "/home/ac"]), Some("/home".into()));
+        assert_eq!(common_root(&["/home/a", "/etc/b"]), Some("/".into()));
+        assert_eq!(common_root(&["/home", "/home/a"]), Some("/".into()));
+        assert_eq!(common_root(&["/a/b"]), Some("/a".into()));
+        assert_eq!(common_root(&["/a"]), Some("/".into()));
+        assert_eq!(common_root(&["/"]), Some("/".into()));
+        assert_eq!(common_root(&["C:\\x\\y", "C:\\x\\z"]), Some("C:\\x".into()));
+        assert_eq!(common_root(&["C:\\x\\y", "C:\\z"]), Some("C:\\".into()));
+        assert_eq!(common_root(&["C:\\x"]), Some("C:\\".into()));
+        assert_eq!(common_root(&["C:"]), Some("C:".into()));
+        assert_eq!(common_root(&["C:\\x\\y", "D:\\x\\y"]), None);
+        assert_eq!(common_root(&["a/b", "c"]), None);
+        assert_eq!(common_root(&[]), None);
+    }
+
+    #[test]
+    fn events_as_ndjson() {
+        let mut meta = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::Uid(Change {
+                from: None,
+                to: Some(1),
+            }),
+        ]);
+        meta.modified = MaybeChange::Change(Change {
+            from: Some(ts(1)),
+            to: Some(ts(2)),
+        });
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::EntryChange(file_changed(0, 1), meta)),
+        ]);
+
+        let mut out = Vec::new();
+        changeset.write_events_ndjson(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 5);
+        for line in out.lines() {
+            let event: ChangeEvent<Timestamp> = serde_json::from_str(line).unwrap();
+            assert!(changeset.changes.contains_key(&event.path));
+        }
+    }
+
+    #[test]
+    fn map_metadata_of_all_entries() {
+        let changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(1, 2)])),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::Deleted(info(vec![MetadataChange::size(1, 2)])),
+            ),
+        ]);
+
+        let mut seen = Vec::new();
+        let changeset = changeset.map_metadata(|path, mut info| {
+            seen.push(path.to_string());
+            info.changes.clear();
+            info
+        });
+        assert_eq!(seen, ["/a", "/b"]);
+        assert!(changeset
+            .changes
+            .values()
+            .all(|diff| diff.meta_info().changes.is_empty()));
+    }
+
+    #[test]
+    fn change_counts() {
+        let mut busy = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::Uid(Change {
+                from: None,
+                to: Some(1),
+            }),
+        ]);
+        busy.accessed = MaybeChange::Change(Change {
+            from: Some(ts(1)),
+            to: Some(ts(2)),
+        });
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(busy)),
+            ("/b", MetaEntryDiff::Added(modified_at(1))),
+        ]);
+
+        let counts = changeset.change_counts();
+        assert_eq!(counts["/a"], 3);
+        assert_eq!(counts["/b"], 0);
+    }
+
+    #[test]
+    fn format_fixed_has_constant_width() {
+        let whole = ts(5).format_fixed();
+        let precise = Timestamp::from(
+            time::OffsetDateTime::from_unix_timestamp_nanos(5_123_456_789).unwrap(),
+        )
+        .format_fixed();
+        assert_eq!(whole, "1970-01-01 00:00:05.000000000");
+        assert_eq!(precise, "1970-01-01 00:00:05.123456789");
+        assert_eq!(whole.len(), precise.len());
+    }
+
+    #[test]
+    fn merge_reporting() {
+        let a = changeset(vec![
+            (
+                "/d",
+                MetaEntryDiff::EntryChange(file_changed(0, 1), info(vec![])),
+            ),
+            (
+                "/f",
+                MetaEntryDiff::EntryChange(
+                    file_changed(0, 1),
+                    info(vec![MetadataChange::size(1, 2)]),
+                ),
+            ),
+            ("/gone", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/tmp", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        let b = changeset(vec![
+            (
+                "/d",
+                MetaEntryDiff::EntryChange(file_changed(7, 8), info(vec![])),
+            ),
+            (
+                "/f",
+                MetaEntryDiff::EntryChange(
+                    file_changed(1, 2),
+                    info(vec![MetadataChange::size(2, 5)]),
+                ),
+            ),
+            (
+                "/gone",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            ),
+            ("/new", MetaEntryDiff::Added(info(vec![]))),
+            ("/tmp", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+
+        let (merged, conflicts) = a.merge_reporting(b);
+        assert_eq!(
+            merged.changes["/f"],
+            MetaEntryDiff::EntryChange(file_changed(0, 2), info(vec![MetadataChange::size(1, 5)]))
+        );
+        assert!(!merged.changes.contains_key("/tmp"));
+        assert!(merged.changes.contains_key("/new"));
+        assert_eq!(
+            conflicts,
+            [
+                MergeConflict {
+                    path: "/d".into(),
+                    kind: MergeConflictKind::Discontinuous
+                },
+                MergeConflict {
+                    path: "/gone".into(),
+                    kind: MergeConflictKind::ChangedAfterDeletion
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn entries_of_kind() {
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/c", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/d", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+
+        assert_eq!(
+            changeset
+                .of_kind(MetaEntryDiffKind::Deleted)
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>(),
+            ["/b", "/c"]
+        );
+    }
+
+    #[test]
+    fn directory_digests() {
+        let changed_to = |to| MetaEntryDiff::EntryChange(file_changed(0, to), info(vec![]));
+        let mut changeset = changeset(vec![
+            ("/a/new", MetaEntryDiff::Added(info(vec![]))),
+            ("/a/x", changed_to(1)),
+            ("/b/y", changed_to(2)),
+        ]);
+
+        let before = changeset.directory_digests();
+        assert_eq!(before.keys().collect::<Vec<_>>(), ["/", "/a", "/b"]);
+
+        changeset.changes.insert("/a/x".into(), changed_to(3));
+        let after = changeset.directory_digests();
+        assert_ne!(before["/a"], after["/a"]);
+        assert_ne!(before["/"], after["/"]);
+        assert_eq!(before["/b"], after["/b"]);
+    }
+
+    #[test]
+    fn maybe_change_constructors() {
+        assert!(!MaybeChange::same(3).is_changed());
+        assert!(MaybeChange::changed(1, 2).is_changed());
+        assert_eq!(
+            MaybeChange::<Option<u8>>::unchanged_none(),
+            MaybeChange::Same(None)
+        );
+    }
+
+    #[test]
+    fn top_size_changes() {
+        let changeset = changeset(vec![
+            (
+                "/grow",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(0, 1 << 30)])),
+            ),
+            ("/none", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            (
+                "/shrink",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(500 << 20, 0)])),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset.top_size_changes(1),
+            [(&"/grow".to_string(), 1 << 30)]
+        );
+        assert_eq!(
+            changeset.top_size_changes(5),
+            [
+                (&"/grow".to_string(), 1 << 30),
+                (&"/shrink".to_string(), -(500 << 20)),
+            ]
+        );
+    }
+
+    #[test]
+    fn attributes_of_file_content_change() {
+        let diff = MetaEntryDiff::EntryChange(
+            file_changed(0, 1),
+            info(vec![
+                MetadataChange::size(10, 4),
+                MetadataChange::UnixPermissions(Change {
+                    from: None,
+                    to: Some(0o755),
+                }),
+            ]),
+        );
+
+        let attributes = diff.to_attributes("/x");
+        assert_eq!(
+            attributes.keys().map(String::as_str).collect::<Vec<_>>(),
+            [
+                "change.kind",
+                "file.hash.new",
+                "file.hash.old",
+                "file.mode.new",
+                "file.path",
+                "file.size.delta",
+                "file.size.new",
+                "file.size.old",
+            ]
+        );
+        assert_eq!(attributes["change.kind"], "entry_change");
+        assert_eq!(attributes["file.hash.old"], "00".repeat(32));
+        assert_eq!(attributes["file.hash.new"], "01".repeat(32));
+        assert_eq!(attributes["file.mode.new"], "755");
+        assert_eq!(attributes["file.path"], "/x");
+        assert_eq!(attributes["file.size.delta"], "-6");
+    }
+
+    #[test]
+    fn filter_by_category() {
+        let uid = MetadataChange::Uid(Change {
+            from: Some(1),
+            to: Some(2),
+        });
+        let permissions = MetadataChange::UnixPermissions(Change {
+            from: Some(0o644),
+            to: Some(0o755),
+        });
+        let changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::MetaOnlyChange(info(vec![
+                    MetadataChange::size(1, 2),
+                    uid.clone(),
+                    permissions.clone(),
+                ])),
+            ),
+            ("/b", MetaEntryDiff::MetaOnlyChange(info(vec![uid]))),
+        ]);
+
+        let filtered = changeset.filter_by_category(&[MetadataChangeCategory::Permissions]);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(
+            filtered.get("/a").unwrap().meta_info().changes,
+            [permissions]
+        );
+    }
+
+    #[test]
+    fn created_empty_files() {
+        let changeset = changeset(vec![
+            (
+                "/data",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 5)])),
+            ),
+            ("/gone", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/lock", MetaEntryDiff::Added(info(vec![]))),
+            (
+                "/zero",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 0)])),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset.created_empty_files().collect::<Vec<_>>(),
+            ["/lock", "/zero"]
+        );
+    }
+
+    #[test]
+    fn schema_version() {
+        let changeset = changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]);
+        let value = changeset.to_json_value().unwrap();
+        assert_eq!(value["schema_version"], Changeset::SCHEMA_VERSION);
+
+        let mut legacy = value.clone();
+        legacy.as_object_mut().unwrap().remove("schema_version");
+        let loaded = Changeset::<Timestamp>::from_json_value(legacy).unwrap();
+        assert_eq!(loaded.schema_version, 1);
+        assert_eq!(loaded, changeset);
+
+        let mut future = value;
+        let future_version = Changeset::SCHEMA_VERSION + 1;
+        future["schema_version"] = future_version.into();
+        let err = Changeset::<Timestamp>::from_json(&future.to_string()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("unsupported schema version {future_version}")),
+            "{err}"
+        );
+        assert!(Changeset::<Timestamp>::from_json_strict(&future.to_string()).is_err());
+    }
+
+    #[test]
+    fn paths_by_inode() {
+        let with_inode = |inode| {
+            let mut info = info(vec![]);
+            info.inode = MaybeChange::Same(Some(inode));
+            info
+        };
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(with_inode(100))),
+            ("/b", MetaEntryDiff::Added(with_inode(100))),
+            ("/c", MetaEntryDiff::Added(with_inode(7))),
+            ("/d", MetaEntryDiff::Deleted(with_inode(100))),
+            ("/e", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+
+        let paths = changeset.paths_by_inode();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[&100], ["/a", "/b"]);
+        assert_eq!(paths[&7], ["/c"]);
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let mut previous = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+            (
+                "/c",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            ),
+        ]);
+        previous.annotate("/a", serde_json::json!("x"));
+        previous.annotate("/c", serde_json::json!("y"));
+        let mut current = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            (
+                "/c",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 3)])),
+            ),
+            ("/d", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        current.annotate("/a", serde_json::json!("x"));
+        current.earliest_timestamp = ts(5);
+
+        let delta = current.delta_from(&previous);
+        assert_eq!(delta.changes.keys().collect::<Vec<_>>(), ["/c", "/d"]);
+        assert_eq!(delta.removed.iter().collect::<Vec<_>>(), ["/b"]);
+        assert!(delta.annotations.is_empty());
+        assert_eq!(delta.removed_annotations.iter().collect::<Vec<_>>(), ["/c"]);
+        assert_eq!(previous.apply_delta(&delta), current);
+        assert_eq!(current.apply_delta(&current.delta_from(&current)), current);
+    }
+
+    #[test]
+    fn redact_hashes() {
+        let hashes = |changeset: &Changeset<Timestamp>| match &changeset.changes["/a"] {
+            MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) => {
+                hash_change.clone()
+            }
+            diff => panic!("unexpected diff {diff:?}"),
+        };
+        let original = changeset(vec![(
+            "/a",
+            MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+        )]);
+
+        let mut old_only = original.clone();
+        old_only.keep_only_old_hashes();
+        assert_eq!(
+            hashes(&old_only),
+            Change {
+                from: Hash([1; 32]),
+                to: Hash([0; 32])
+            }
+        );
+
+        let mut new_only = original;
+        new_only.keep_only_new_hashes();
+        assert_eq!(
+            hashes(&new_only),
+            Change {
+                from: Hash([0; 32]),
+                to: Hash([2; 32])
+            }
+        );
+    }
+
+    #[test]
+    fn iter_with_depth() {
+        let changeset = changeset(vec![
+            ("/etc", MetaEntryDiff::Added(info(vec![]))),
+            ("/etc/passwd", MetaEntryDiff::Added(info(vec![]))),
+            ("C:\\Windows\\a.dll", MetaEntryDiff::Added(info(vec![]))),
+            ("rel", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+
+        assert_eq!(
+            changeset
+                .iter_with_depth()
+                .map(|(depth, path, _)| (depth, path.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (1, "/etc"),
+                (2, "/etc/passwd"),
+                (2, "C:\\Windows\\a.dll"),
+                (0, "rel"),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_stream_bytes() {
+        let stream = |from: Option<&[u8]>, to: Option<&[u8]>| {
+            MetadataChange::NamedStream(
+                NamedStreamType::ExtendedAttributes,
+                Change {
+                    from: from.map(Into::into),
+                    to: to.map(Into::into),
+                },
+            )
+        };
+        let streams = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::MetaOnlyChange(info(vec![
+                    stream(Some(&[0; 3]), Some(&[0; 5])),
+                    MetadataChange::size(1, 100),
+                ])),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::Added(info(vec![stream(None, Some(&[0; 10]))])),
+            ),
+        ]);
+
+        assert_eq!(streams.named_stream_bytes(), 18);
+        assert_eq!(changeset(vec![]).named_stream_bytes(), 0);
+    }
+
+    #[test]
+    fn metadata_change_constructors() {
+        assert_eq!(
+            MetadataChange::size(1, 2),
+            MetadataChange::Size(Change { from: 1, to: 2 })
+        );
+        assert_eq!(
+            MetadataChange::from(Change { from: 1, to: 2 }),
+            MetadataChange::size(1, 2)
+        );
+        assert_eq!(
+            MetadataChange::uid(None, Some(3)),
+            MetadataChange::Uid(Change {
+                from: None,
+                to: Some(3)
+            })
+        );
+        assert_eq!(
+            MetadataChange::unix_permissions(Some(0o644), None),
+            MetadataChange::UnixPermissions(Change {
+                from: Some(0o644),
+                to: None
+            })
+        );
+    }
+
+    #[test]
+    fn efs_changes() {
+        let stream = |ty| {
+            MetadataChange::NamedStream(
+                ty,
+                Change {
+                    from: None,
+                    to: Some([1].as_slice().into()),
+                },
+            )
+        };
+        let changeset = changeset(vec![
+            (
+                "/enc",
+                MetaEntryDiff::MetaOnlyChange(info(vec![
+                    MetadataChange::size(1, 2),
+                    stream(NamedStreamType::EncryptedFileSystemInfo),
+                ])),
+            ),
+            (
+                "/other",
+                MetaEntryDiff::MetaOnlyChange(info(vec![stream(
+                    NamedStreamType::ExtendedAttributes,
+                )])),
+            ),
+        ]);
+
+        assert_eq!(changeset.efs_changes().collect::<Vec<_>>(), ["/enc"]);
+        assert_eq!(changeset.efs_changes().count(), 1);
+    }
+
+    #[test]
+    fn timestamp_pairs() {
+        let mut info = modified_at(1);
+        info.accessed = MaybeChange::Change(Change {
+            from: Some(ts(1)),
+            to: Some(ts(2)),
+        });
+
+        let pairs = info.timestamp_pairs();
+        assert_eq!(pairs[0], (TimestampKind::Created, None, None));
+        assert_eq!(
+            pairs[1],
+            (TimestampKind::Modified, Some(&ts(1)), Some(&ts(1)))
+        );
+        assert_eq!(
+            pairs[2],
+            (TimestampKind::Accessed, Some(&ts(1)), Some(&ts(2)))
+        );
+        assert_eq!(pairs[3], (TimestampKind::InodeModified, None, None));
+    }
+
+    #[test]
+    fn streaming_writer() {
+        let expected = changeset(vec![
+            (
+                "/a\"q",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 3)])),
+            ),
+            ("/b", MetaEntryDiff::Deleted(info(vec![]))),
+        ]);
+
+        let mut writer = ChangesetWriter::new(Vec::new(), &expected.earliest_timestamp).unwrap();
+        for (path, diff) in &expected.changes {
+            writer.write_entry(path, diff).unwrap();
+        }
+        let out = String::from_utf8(writer.finish().unwrap()).unwrap();
+        assert_eq!(out, serde_json::to_string(&expected).unwrap());
+        assert_eq!(Changeset::from_json(&out).unwrap(), expected);
+
+        let empty = ChangesetWriter::new(Vec::new(), &ts(0))
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            Changeset::from_json(std::str::from_utf8(&empty).unwrap()).unwrap(),
+            changeset(vec![])
+        );
+
+        let mut writer = ChangesetWriter::new(Vec::new(), &ts(0)).unwrap();
+        let deleted = &expected.changes["/b"];
+        writer.write_entry("/b", deleted).unwrap();
+        assert_eq!(
+            writer.write_entry("/a", deleted).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        assert!(writer.write_entry("/b", deleted).is_err());
+    }
+
+    #[test]
+    fn exclude_allowlist() {
+        let changeset = changeset(vec![
+            ("/etc/passwd", MetaEntryDiff::MetaOnlyChange(modified_at(7))),
+            ("/logs", MetaEntryDiff::Added(info(vec![]))),
+            ("/tmp/cache/q", MetaEntryDiff::Added(modified_at(5))),
+            (
+                "/var/log/a.log",
+                MetaEntryDiff::MetaOnlyChange(modified_at(1)),
+            ),
+            ("/x.log", MetaEntryDiff::MetaOnlyChange(modified_at(2))),
+        ]);
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        let filtered = changeset.exclude_allowlist(&patterns(&["*.log", "/tmp/ca?he/*"]));
+        assert_eq!(
+            filtered.changes.keys().collect::<Vec<_>>(),
+            ["/etc/passwd", "/logs"]
+        );
+        assert_eq!(filtered.earliest_timestamp, ts(7));
+        assert_eq!(changeset.exclude_allowlist(&[]).earliest_timestamp, ts(1));
+
+        let everything = changeset.exclude_allowlist(&patterns(&["*"]));
+        assert!(everything.is_empty());
+        assert_eq!(everything.earliest_timestamp, ts(0));
+        assert_eq!(
+            changeset
+                .exclude_allowlist(&patterns(&["/etc/*wd", "**s"]))
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn coalesce_named_streams() {
+        let mut streams = info(vec![
+            ads_change("x", None, Some(&[1])),
+            MetadataChange::size(1, 2),
+            ads_change("y", Some(&[5]), Some(&[6])),
+            ads_change("x", Some(&[1]), Some(&[2])),
+            ads_change("y", Some(&[6]), Some(&[5])),
+        ]);
+        streams.coalesce_named_streams();
+        assert_eq!(
+            streams.changes,
+            [
+                ads_change("x", None, Some(&[2])),
+                MetadataChange::size(1, 2)
+            ]
+        );
+
+        let mut changeset = changeset(vec![(
+            "/a",
+            MetaEntryDiff::MetaOnlyChange(info(vec![
+                ads_change("x", None, Some(&[1])),
+                ads_change("x", Some(&[1]), Some(&[3])),
+            ])),
+        )]);
+        changeset.coalesce_named_streams();
+        assert_eq!(
+            changeset.changes["/a"].meta_info().changes,
+            [ads_change("x", None, Some(&[3]))]
+        );
+    }
+
+    #[test]
+    fn kind_counts() {
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            ("/b", MetaEntryDiff::Added(info(vec![]))),
+            ("/c", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            (
+                "/d",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+            (
+                "/e",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+            (
+                "/f",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+        ]);
+
+        assert_eq!(changeset.kind_counts(), [2, 0, 1, 3]);
+        assert_eq!(changeset.kind_count(MetaEntryDiffKind::EntryChange), 3);
+        assert_eq!(changeset.kind_count(MetaEntryDiffKind::Deleted), 0);
+    }
+
+    #[test]
+    fn inode_changed_same_content() {
+        let moved = || {
+            let mut info = info(vec![]);
+            info.inode = MaybeChange::changed(Some(1), Some(2));
+            info
+        };
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::MetaOnlyChange(moved())),
+            (
+                "/b",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), moved()),
+            ),
+            (
+                "/c",
+                MetaEntryDiff::MetaOnlyChange(info(vec![MetadataChange::size(1, 2)])),
+            ),
+            ("/d", MetaEntryDiff::Added(moved())),
+            (
+                "/e",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, moved()),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset.inode_changed_same_content().collect::<Vec<_>>(),
+            ["/a", "/e"]
+        );
+    }
+
+    #[test]
+    fn risk_scores() {
+        let mut setuid = info(vec![MetadataChange::unix_permissions(
+            Some(0o755),
+            Some(0o4755),
+        )]);
+        setuid.modified = MaybeChange::Same(Some(ts(1)));
+        let mut access = info(vec![]);
+        access.accessed = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let mut changeset = changeset(vec![
+            ("/access", MetaEntryDiff::MetaOnlyChange(access)),
+            ("/setuid", MetaEntryDiff::MetaOnlyChange(setuid)),
+        ]);
+        assert!(!changeset.to_pretty_json().unwrap().contains("risk_scores"));
+
+        changeset.score_risks(default_risk_score);
+        assert!(
+            changeset.risk_score("/setuid").unwrap() > changeset.risk_score("/access").unwrap()
+        );
+        assert_eq!(changeset.risk_score("/access"), Some(0));
+        assert_eq!(changeset.risk_score("/missing"), None);
+        assert_eq!(
+            Changeset::from_json(&changeset.to_pretty_json().unwrap()).unwrap(),
+            changeset
+        );
+
+        changeset.score_risks(|_| 200);
+        assert_eq!(changeset.risk_score("/access"), Some(100));
+        assert_eq!(changeset.subset(["/access"]).risk_scores.len(), 1);
+    }
+
+    #[test]
+    fn ads_name_frequency() {
+        let zone = || ads_change("Zone.Identifier", None, Some(&[1]));
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![zone()]))),
+            (
+                "/b",
+                MetaEntryDiff::Added(info(vec![
+                    zone(),
+                    ads_change("evil", None, Some(&[1])),
+                    MetadataChange::size(0, 1),
+                ])),
+            ),
+            (
+                "/c",
+                MetaEntryDiff::Added(info(vec![MetadataChange::NamedStream(
+                    NamedStreamType::DosName,
+                    Change {
+                        from: None,
+                        to: None,
+                    },
+                )])),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset
+                .ads_name_frequency()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [("Zone.Identifier".to_string(), 2), ("evil".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn normalize_is_idempotent() {
+        let at = |nanos| {
+            Timestamp::from(
+                time::OffsetDateTime::from_unix_timestamp(5).unwrap()
+                    + time::Duration::nanoseconds(nanos),
+            )
+        };
+        let mut subsecond = info(vec![]);
+        subsecond.modified = MaybeChange::changed(Some(at(1)), Some(at(2)));
+        let mut duplicated = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::size(3, 3),
+            MetadataChange::size(1, 2),
+        ]);
+        duplicated.inode = MaybeChange::changed(Some(9), Some(9));
+        let mut changeset = changeset(vec![
+            ("/add", MetaEntryDiff::Added(info(vec![]))),
+            ("/dup", MetaEntryDiff::MetaOnlyChange(duplicated)),
+            ("/sub", MetaEntryDiff::MetaOnlyChange(subsecond)),
+        ]);
+        changeset.annotate("/sub", serde_json::json!(1));
+
+        let options = NormalizeOptions {
+            truncate_timestamps: Some(TimePrecision::Seconds),
+            ..Default::default()
+        };
+        changeset.normalize(options);
+        assert_eq!(
+            changeset.changes.keys().collect::<Vec<_>>(),
+            ["/add", "/dup"]
+        );
+        assert!(changeset.annotations.is_empty());
+        let dup = changeset.changes["/dup"].meta_info();
+        assert_eq!(dup.changes, [MetadataChange::size(1, 2)]);
+        assert_eq!(dup.inode, MaybeChange::Same(Some(9)));
+
+        let once = changeset.clone();
+        changeset.normalize(options);
+        assert_eq!(changeset, once);
+    }
+
+    #[test]
+    fn match_iocs() {
+        let changed = |from, to| MetaEntryDiff::EntryChange(file_changed(from, to), info(vec![]));
+        let changeset = changeset(vec![
+            ("/bad", changed(1, 2)),
+            ("/new", MetaEntryDiff::Added(info(vec![]))),
+            ("/ok", changed(2, 3)),
+        ]);
+        let iocs = [Hash([2; 32])].into_iter().collect();
+
+        assert_eq!(
+            changeset.match_iocs(&iocs).collect::<Vec<_>>(),
+            [(&"/bad".to_string(), &Hash([2; 32]))]
+        );
+    }
+
+    #[test]
+    fn time_span() {
+        let mut changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(modified_at(100))),
+            ("/b", MetaEntryDiff::Added(modified_at(40))),
+            ("/c", MetaEntryDiff::Added(info(vec![]))),
+        ]);
+        changeset.earliest_timestamp = ts(10);
+
+        assert_eq!(changeset.time_span(), Some((ts(10), ts(100))));
+        assert_eq!(changeset.duration(), Some(time::Duration::seconds(90)));
+
+        changeset.changes.retain(|path, _| path == "/c");
+        assert_eq!(changeset.time_span(), None);
+        assert_eq!(changeset.duration(), None);
+    }
+
+    #[test]
+    fn split_by_root() {
+        let added = || MetaEntryDiff::Added(info(vec![]));
+        let mut changeset = changeset(vec![
+            ("", added()),
+            ("/etc/passwd", added()),
+            ("C:", added()),
+            ("C:/x", added()),
+            ("C:\\Windows\\a.dll", added()),
+            ("D:\\data", added()),
+            ("\\\\srv\\other", added()),
+            ("\\\\srv\\share\\f", added()),
+            ("rel/p", added()),
+        ]);
+        changeset.annotate("D:\\data", serde_json::json!(1));
+        changeset.earliest_timestamp = ts(3);
+
+        let split = changeset.split_by_root();
+        assert_eq!(
+            split
+                .iter()
+                .map(|(root, changeset)| (root.as_str(), changeset.len()))
+                .collect::<Vec<_>>(),
+            [
+                ("", 2),
+                ("/", 1),
+                ("C:", 3),
+                ("D:", 1),
+                ("\\\\srv\\other", 1),
+                ("\\\\srv\\share", 1),
+            ]
+        );
+        assert_eq!(split["D:"].earliest_timestamp, ts(3));
+        assert_eq!(split["D:"].annotations.len(), 1);
+        assert!(split["C:"].annotations.is_empty());
+    }
+
+    #[test]
+    fn entry_diff_constructors() {
+        assert_eq!(
+            EntryDiff::file_changed(Hash([1; 32]), Hash([2; 32])),
+            EntryDiff::FileChanged {
+                hash_change: Change {
+                    from: Hash([1; 32]),
+                    to: Hash([2; 32])
+                }
+            }
+        );
+        assert_eq!(
+            EntryDiff::symlink_changed("a".into(), "b".into()),
+            EntryDiff::SymlinkChanged {
+                path_change: Change {
+                    from: "a".into(),
+                    to: "b".into()
+                }
+            }
+        );
+        assert_eq!(
+            EntryDiff::type_change("file".into(), "dir".into()),
+            EntryDiff::TypeChange(Change {
+                from: "file".into(),
+                to: "dir".into()
+            })
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_round_trip() {
+        let mut meta = info(vec![
+            MetadataChange::size(1, 2),
+            ads_change("z", None, Some(&[1, 2])),
+        ]);
+        meta.modified = MaybeChange::changed(Some(ts(1)), Some(ts(2)));
+        let mut changeset = changeset(vec![
+            (
+                "/a",
+                MetaEntryDiff::EntryChange(file_changed(0xab, 0xcd), meta),
+            ),
+            (
+                "/b",
+                MetaEntryDiff::EntryChange(file_changed(0x11, 0x22), info(vec![])),
+            ),
+        ]);
+        changeset.annotate("/a", serde_json::json!({ "k": [1, "x"] }));
+
+        let yaml = changeset.to_yaml().unwrap();
+        assert!(yaml.contains(&"ab".repeat(32)), "{yaml}");
+        // A hash consisting only of digits would be read back as a number if it weren't quoted.
+        assert!(yaml.contains(&format!("'{}'", "11".repeat(32))), "{yaml}");
+        assert!(yaml.contains("1970-01-01 00:00:02"), "{yaml}");
+        assert_eq!(Changeset::from_yaml(&yaml).unwrap(), changeset);
+    }
+
+    #[test]
+    fn permission_loosening() {
+        let unix = |from, to| {
+            MetadataChange::unix_permissions(Some(from), Some(to)).is_permission_loosening()
+        };
+        assert!(unix(0o600, 0o666));
+        assert!(!unix(0o666, 0o600));
+        assert!(unix(0o755, 0o4755));
+        assert!(unix(0o1777, 0o777));
+        assert!(!unix(0o777, 0o1777));
+        assert!(!unix(0o100644, 0o644));
+        assert!(MetadataChange::unix_permissions(None, Some(0o644)).is_permission_loosening());
+
+        let ntfs = |from: NtfsAttributes, to: NtfsAttributes| {
+            MetadataChange::ntfs_attributes(Some(from.bits()), Some(to.bits()))
+                .is_permission_loosening()
+        };
+        let archive = NtfsAttributes::ARCHIVE;
+        assert!(ntfs(archive | NtfsAttributes::READONLY, archive));
+        assert!(!ntfs(archive, archive | NtfsAttributes::READONLY));
+        assert!(!ntfs(archive, archive | NtfsAttributes::HIDDEN));
+
+        assert!(!MetadataChange::size(1, 2).is_permission_loosening());
+    }
+
+    #[test]
+    fn apply_to_snapshot() {
+        let mut snapshot = Snapshot::default();
+        snapshot.entries.insert(
+            "/f".into(),
+            SnapshotEntry {
+                hash: Some(Hash([1; 32])),
+                size: 10,
+                ..Default::default()
+            },
+        );
+        snapshot
+            .entries
+            .insert("/gone".into(), SnapshotEntry::default());
+        let changeset = changeset(vec![
+            (
+                "/f",
+                MetaEntryDiff::EntryChange(
+                    file_changed(1, 2),
+                    info(vec![
+                        MetadataChange::size(10, 20),
+                        MetadataChange::uid(None, Some(5)),
+                    ]),
+                ),
+            ),
+            ("/gone", MetaEntryDiff::Deleted(info(vec![]))),
+            (
+                "/new",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 3)])),
+            ),
+        ]);
+
+        changeset.apply_to_snapshot(&mut snapshot).unwrap();
+        assert_eq!(
+            snapshot.entries["/f"],
+            SnapshotEntry {
+                hash: Some(Hash([2; 32])),
+                size: 20,
+                uid: Some(5),
+                ..Default::default()
+            }
+        );
+        assert!(!snapshot.entries.contains_key("/gone"));
+        assert_eq!(snapshot.entries["/new"].size, 3);
+
+        let errors = changeset.apply_to_snapshot(&mut snapshot).unwrap_err();
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (error.path.as_str(), error.kind))
+                .collect::<Vec<_>>(),
+            [
+                ("/f", ApplyErrorKind::Mismatch(SnapshotField::Hash)),
+                ("/f", ApplyErrorKind::Mismatch(SnapshotField::Size)),
+                ("/f", ApplyErrorKind::Mismatch(SnapshotField::Uid)),
+                ("/gone", ApplyErrorKind::Missing),
+                ("/new", ApplyErrorKind::AlreadyPresent),
+            ]
+        );
+    }
+
+    #[test]
+    fn meta_entry_diff_iterator_stats() {
+        let diffs: Vec<MetaEntryDiff<Timestamp>> = vec![
+            MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 10)])),
+            MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![MetadataChange::size(10, 4)])),
+            MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            MetaEntryDiff::Deleted(info(vec![MetadataChange::size(7, 0)])),
+        ];
+
+        assert_eq!(diffs.iter().kind_counts(), [1, 1, 0, 2]);
+        assert_eq!(diffs.iter().total_size_delta(), -3);
+        assert_eq!(diffs.iter().content_changes(), 1);
+        assert_eq!(
+            diffs
+                .iter()
+                .filter(|diff| diff.kind() != MetaEntryDiffKind::Added)
+                .total_size_delta(),
+            -13
+        );
+    }
+
+    #[test]
+    fn timestamps_with_t_separator() {
+        let iso: Timestamp = serde_json::from_str("\"2023-01-02T03:04:05.1\"").unwrap();
+        let space: Timestamp = serde_json::from_str("\"2023-01-02 03:04:05.1\"").unwrap();
+        assert_eq!(iso, space);
+        assert_eq!(
+            serde_json::to_string(&iso).unwrap(),
+            "\"2023-01-02 03:04:05.1\""
+        );
+        assert!(serde_json::from_str::<Timestamp>("\"2023-01-02X03:04:05\"").is_err());
+    }
+
+    #[test]
+    fn content_ratio() {
+        let changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(info(vec![]))),
+            (
+                "/b",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+            ("/c", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            ("/d", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+        ]);
+
+        assert_eq!(changeset.content_ratio(), 0.5);
+        assert_eq!(changeset.subset([]).content_ratio(), 0.0);
+    }
+
+    #[test]
+    fn typed_hash() {
+        let hex = "ab".repeat(32);
+        let blake3: TypedHash = serde_json::from_str(&format!("\"blake3:{hex}\"")).unwrap();
+        assert_eq!(
+            blake3,
+            TypedHash {
+                algorithm: HashAlgorithm::Blake3,
+                digest: Hash([0xab; 32])
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&blake3).unwrap(),
+            format!("\"blake3:{hex}\"")
+        );
+
+        let sha256: TypedHash = serde_json::from_str(&format!("\"sha256:{hex}\"")).unwrap();
+        assert_eq!(sha256, TypedHash::from(Hash([0xab; 32])));
+        assert_eq!(TypedHash::try_from(hex.as_str()).unwrap(), sha256);
+
+        assert_eq!(
+            TypedHash::try_from(format!("md5:{hex}")).unwrap_err(),
+            "unknown hash algorithm `md5`"
+        );
+        assert!(TypedHash::try_from("sha256:zz").is_err());
+    }
+
+    #[test]
+    fn entries_in_size_range() {
+        const MB: u64 = 1 << 20;
+        let changeset = changeset(vec![
+            (
+                "/big",
+                MetaEntryDiff::EntryChange(
+                    EntryDiff::OtherChange,
+                    info(vec![MetadataChange::size(0, 10 * MB)]),
+                ),
+            ),
+            ("/empty", MetaEntryDiff::Added(info(vec![]))),
+            (
+                "/gone",
+                MetaEntryDiff::Deleted(info(vec![MetadataChange::size(10 * MB, 0)])),
+            ),
+            ("/meta", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            (
+                "/small",
+                MetaEntryDiff::Added(info(vec![MetadataChange::size(0, 500)])),
+            ),
+        ]);
+
+        assert_eq!(
+            changeset
+                .entries_in_size_range(MB, 100 * MB)
+                .collect::<Vec<_>>(),
+            ["/big"]
+        );
+        assert_eq!(
+            changeset.entries_in_size_range(0, 500).collect::<Vec<_>>(),
+            ["/empty", "/small"]
+        );
+    }
+
+    #[test]
+    fn timeline() {
+        let mut changeset = changeset(vec![
+            ("/a", MetaEntryDiff::Added(modified_at(1010))),
+            ("/b", MetaEntryDiff::Added(modified_at(4599))),
+            ("/c", MetaEntryDiff::Added(modified_at(4605))),
+            ("/d", MetaEntryDiff::Added(info(vec![]))),
+            ("/e", MetaEntryDiff::Added(modified_at(999))),
+        ]);
+        changeset.earliest_timestamp = ts(1000);
+
+        assert_eq!(
+            changeset
+                .timeline(time::Duration::hours(1))
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [(ts(1000 - 3600), 1), (ts(1000), 2), (ts(4600), 1)]
+        );
+        assert!(changeset.timeline(time::Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn null_hash() {
+        assert!(Hash::NULL.is_null());
+        assert!(!Hash([7; 32]).is_null());
+        let mut bytes = [0; 32];
+        bytes[31] = 1;
+        assert!(!Hash(bytes).is_null());
+    }
+
+    #[test]
+    fn type_transitions() {
+        let type_change = |from: &str, to: &str| {
+            MetaEntryDiff::EntryChange(EntryDiff::type_change(from.into(), to.into()), info(vec![]))
+        };
+        let changeset = changeset(vec![
+            ("/a", type_change("file", "directory")),
+            ("/b", type_change("file", "directory")),
+            ("/c", type_change("file", "symlink")),
+            (
+                "/d",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+        ]);
+
+        let transitions = changeset.type_transitions();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(
+            transitions[&("file".to_string(), "directory".to_string())],
+            ["/a", "/b"]
+        );
+        assert_eq!(
+            transitions[&("file".to_string(), "symlink".to_string())],
+            ["/c"]
+        );
+    }
 }
@@ -0,0 +1,815 @@
+//! Protobuf representations of the changeset types.
+//!
+//! The types in this module mirror `proto/sniff_interop.proto` and can be encoded and decoded
+//! using `prost::Message`.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::Timestamp;
+
+/// Represents a set of changes for a whole diff tree.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Changeset {
+    /// The earliest possible in this changeset.
+    #[prost(message, optional, tag = "1")]
+    pub earliest_timestamp: Option<prost_types::Timestamp>,
+    /// All the changes in this change set, keyed by their path.
+    #[prost(btree_map = "string, message", tag = "2")]
+    pub changes: BTreeMap<String, MetaEntryDiff>,
+    /// Arbitrary annotations of paths, encoded as JSON.
+    #[prost(btree_map = "string, string", tag = "3")]
+    pub annotations: BTreeMap<String, String>,
+    /// The risk scores of paths, each between 0 and 100.
+    #[prost(btree_map = "string, uint32", tag = "4")]
+    pub risk_scores: BTreeMap<String, u32>,
+}
+
+/// Represents a change of a file system entry and its associated metadata.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MetaEntryDiff {
+    /// The kind of the change.
+    #[prost(oneof = "meta_entry_diff::Kind", tags = "1, 2, 3, 4")]
+    pub kind: Option<meta_entry_diff::Kind>,
+}
+
+/// Nested types of `MetaEntryDiff`.
+pub mod meta_entry_diff {
+    /// The kind of the change.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        /// The entry was added.
+        #[prost(message, tag = "1")]
+        Added(super::MetadataInfo),
+        /// The entry was deleted.
+        #[prost(message, tag = "2")]
+        Deleted(super::MetadataInfo),
+        /// Only the metadata changed.
+        #[prost(message, tag = "3")]
+        MetaOnlyChange(super::MetadataInfo),
+        /// The entry changed (and with it likely the metadata too).
+        #[prost(message, tag = "4")]
+        EntryChange(super::EntryChange),
+    }
+}
+
+/// A change of the entry together with its metadata.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntryChange {
+    /// The change of the entry.
+    #[prost(message, optional, tag = "1")]
+    pub entry: Option<EntryDiff>,
+    /// The metadata of the entry.
+    #[prost(message, optional, tag = "2")]
+    pub info: Option<MetadataInfo>,
+}
+
+/// Represents a change of a file system entry.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntryDiff {
+    /// The kind of the change.
+    #[prost(oneof = "entry_diff::Kind", tags = "1, 2, 3, 4")]
+    pub kind: Option<entry_diff::Kind>,
+}
+
+/// Nested types of `EntryDiff`.
+pub mod entry_diff {
+    /// The kind of the change.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        /// The underlying file has changed.
+        #[prost(message, tag = "1")]
+        FileChanged(super::HashChange),
+        /// The underlying symlink has changed.
+        #[prost(message, tag = "2")]
+        SymlinkChanged(super::StringChange),
+        /// The type of the entry has changed.
+        #[prost(message, tag = "3")]
+        TypeChange(super::StringChange),
+        /// Some other change occurred.
+        #[prost(message, tag = "4")]
+        OtherChange(super::OtherChange),
+    }
+}
+
+/// Some other change occurred.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OtherChange {}
+
+/// The relevant information about the metadata and its changes.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MetadataInfo {
+    /// The changes in this diff.
+    #[prost(message, repeated, tag = "1")]
+    pub changes: Vec<MetadataChange>,
+    /// The inode associated with the metadata.
+    #[prost(message, optional, tag = "2")]
+    pub inode: Option<MaybeOptionalU64>,
+    /// The timestamp of creation associated with the metadata.
+    #[prost(message, optional, tag = "3")]
+    pub created: Option<MaybeOptionalTimestamp>,
+    /// The timestamp of the last modification associated with the metadata.
+    #[prost(message, optional, tag = "4")]
+    pub modified: Option<MaybeOptionalTimestamp>,
+    /// The timestamp of the last access associated with the metadata.
+    #[prost(message, optional, tag = "5")]
+    pub accessed: Option<MaybeOptionalTimestamp>,
+    /// The timestamp of the last inode modification associated with the metadata.
+    #[prost(message, optional, tag = "6")]
+    pub inode_modified: Option<MaybeOptionalTimestamp>,
+}
+
+/// Represents a single change in the metadata.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MetadataChange {
+    /// The kind of the change.
+    #[prost(oneof = "metadata_change::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub kind: Option<metadata_change::Kind>,
+}
+
+/// Nested types of `MetadataChange`.
+pub mod metadata_change {
+    /// The kind of the change.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        /// The size changed.
+        #[prost(message, tag = "1")]
+        Size(super::U64Change),
+        /// The NFTS attributes changed.
+        #[prost(message, tag = "2")]
+        NtfsAttributes(super::OptionalU32Change),
+        /// The unix permissions changed.
+        #[prost(message, tag = "3")]
+        UnixPermissions(super::OptionalU32Change),
+        /// The number of links to the path changed.
+        #[prost(message, tag = "4")]
+        Nlink(super::OptionalU64Change),
+        /// The user id changed.
+        #[prost(message, tag = "5")]
+        Uid(super::OptionalU32Change),
+        /// The group id changed.
+        #[prost(message, tag = "6")]
+        Gid(super::OptionalU32Change),
+        /// A named stream associated with the path changed.
+        #[prost(message, tag = "7")]
+        NamedStream(super::NamedStreamChange),
+        /// A change that is not known to this version of the schema.
+        #[prost(message, tag = "8")]
+        Unknown(super::UnknownMetadataChange),
+    }
+}
+
+/// The kinds of named streams associated with a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum NamedStreamKind {
+    /// No kind was specified.
+    Unspecified = 0,
+    /// The NTFS reparse data of a path.
+    ReparseData = 1,
+    /// The NTFS access control list of a path.
+    AccessControlList = 2,
+    /// The DOS name of a path.
+    DosName = 3,
+    /// The object ID of a path.
+    ObjectId = 4,
+    /// The encrypted file system info of a path.
+    EncryptedFileSystemInfo = 5,
+    /// The extended attributes of a path.
+    ExtendedAttributes = 6,
+    /// An alternate data stream associated with a path.
+    AlternateDataStream = 7,
+}
+
+/// A change of a named stream associated with a path.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct NamedStreamChange {
+    /// The kind of the named stream.
+    #[prost(enumeration = "NamedStreamKind", tag = "1")]
+    pub kind: i32,
+    /// The name of the alternate data stream, if `kind` is an alternate data stream.
+    #[prost(string, tag = "2")]
+    pub name: String,
+    /// The change of the stream contents.
+    #[prost(message, optional, tag = "3")]
+    pub change: Option<OptionalBytesChange>,
+}
+
+/// A metadata change of a kind that is not known to this version of the schema.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UnknownMetadataChange {
+    /// The name of the variant of the change.
+    #[prost(string, tag = "1")]
+    pub tag: String,
+    /// The raw value of the change, encoded as JSON.
+    #[prost(string, tag = "2")]
+    pub json_value: String,
+}
+
+/// A change of a hash.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HashChange {
+    /// The value before the change.
+    #[prost(bytes = "vec", tag = "1")]
+    pub from: Vec<u8>,
+    /// The value after the change.
+    #[prost(bytes = "vec", tag = "2")]
+    pub to: Vec<u8>,
+}
+
+/// A change of a string.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StringChange {
+    /// The value before the change.
+    #[prost(string, tag = "1")]
+    pub from: String,
+    /// The value after the change.
+    #[prost(string, tag = "2")]
+    pub to: String,
+}
+
+/// A change of an integer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct U64Change {
+    /// The value before the change.
+    #[prost(uint64, tag = "1")]
+    pub from: u64,
+    /// The value after the change.
+    #[prost(uint64, tag = "2")]
+    pub to: u64,
+}
+
+/// A change of an optional integer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OptionalU32Change {
+    /// The value before the change.
+    #[prost(uint32, optional, tag = "1")]
+    pub from: Option<u32>,
+    /// The value after the change.
+    #[prost(uint32, optional, tag = "2")]
+    pub to: Option<u32>,
+}
+
+/// A change of an optional integer.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OptionalU64Change {
+    /// The value before the change.
+    #[prost(uint64, optional, tag = "1")]
+    pub from: Option<u64>,
+    /// The value after the change.
+    #[prost(uint64, optional, tag = "2")]
+    pub to: Option<u64>,
+}
+
+/// A change of optional binary data.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OptionalBytesChange {
+    /// The value before the change.
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub from: Option<Vec<u8>>,
+    /// The value after the change.
+    #[prost(bytes = "vec", optional, tag = "2")]
+    pub to: Option<Vec<u8>>,
+}
+
+/// A possibly changed optional integer.
+///
+/// If `changed` is false, `from` and `to` are equal.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MaybeOptionalU64 {
+    /// The value before the possible change.
+    #[prost(uint64, optional, tag = "1")]
+    pub from: Option<u64>,
+    /// The value after the possible change.
+    #[prost(uint64, optional, tag = "2")]
+    pub to: Option<u64>,
+    /// Whether the value was changed.
+    #[prost(bool, tag = "3")]
+    pub changed: bool,
+}
+
+/// A possibly changed optional timestamp.
+///
+/// If `changed` is false, `from` and `to` are equal.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MaybeOptionalTimestamp {
+    /// The value before the possible change.
+    #[prost(message, optional, tag = "1")]
+    pub from: Option<prost_types::Timestamp>,
+    /// The value after the possible change.
+    #[prost(message, optional, tag = "2")]
+    pub to: Option<prost_types::Timestamp>,
+    /// Whether the value was changed.
+    #[prost(bool, tag = "3")]
+    pub changed: bool,
+}
+
+/// Converts a timestamp to its protobuf representation.
+fn timestamp_to_proto(timestamp: &Timestamp) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: timestamp.unix_timestamp(),
+        nanos: timestamp.nanosecond() as i32,
+    }
+}
+
+/// Converts a timestamp from its protobuf representation.
+fn timestamp_from_proto(timestamp: &prost_types::Timestamp) -> Result<Timestamp, String> {
+    let nanos = i128::from(timestamp.seconds) * 1_000_000_000 + i128::from(timestamp.nanos);
+    time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .map(Into::into)
+        .map_err(|err| format!("{err}"))
+}
+
+/// Returns the contained value or an error mentioning the missing field.
+fn required<T>(value: Option<T>, field: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("missing field `{field}`"))
+}
+
+/// Converts a change to its protobuf representation.
+fn change_to_proto<T: Clone, R>(change: &crate::Change<T>, f: impl Fn(T, T) -> R) -> R {
+    f(change.from.clone(), change.to.clone())
+}
+
+/// Converts a possibly changed inode to its protobuf representation.
+fn maybe_u64_to_proto(value: &crate::MaybeChange<Option<u64>>) -> MaybeOptionalU64 {
+    MaybeOptionalU64 {
+        from: *value.old_val(),
+        to: *value.new_val(),
+        changed: value.is_changed(),
+    }
+}
+
+/// Converts a possibly changed inode from its protobuf representation.
+fn maybe_u64_from_proto(value: Option<MaybeOptionalU64>) -> crate::MaybeChange<Option<u64>> {
+    match value {
+        Some(value) if value.changed => crate::MaybeChange::Change(crate::Change {
+            from: value.from,
+            to: value.to,
+        }),
+        Some(value) => crate::MaybeChange::Same(value.to),
+        None => crate::MaybeChange::Same(None),
+    }
+}
+
+/// Converts a possibly changed timestamp to its protobuf representation.
+fn maybe_timestamp_to_proto(
+    value: &crate::MaybeChange<Option<Timestamp>>,
+) -> MaybeOptionalTimestamp {
+    MaybeOptionalTimestamp {
+        from: value.old_val().as_ref().map(timestamp_to_proto),
+        to: value.new_val().as_ref().map(timestamp_to_proto),
+        changed: value.is_changed(),
+    }
+}
+
+/// Converts a possibly changed timestamp from its protobuf representation.
+fn maybe_timestamp_from_proto(
+    value: Option<MaybeOptionalTimestamp>,
+) -> Result<crate::MaybeChange<Option<Timestamp>>, String> {
+    let convert =
+        |ts: Option<prost_types::Timestamp>| ts.as_ref().map(timestamp_from_proto).transpose();
+
+    Ok(match value {
+        Some(value) if value.changed => crate::MaybeChange::Change(crate::Change {
+            from: convert(value.from)?,
+            to: convert(value.to)?,
+        }),
+        Some(value) => crate::MaybeChange::Same(convert(value.to)?),
+        None => crate::MaybeChange::Same(None),
+    })
+}
+
+impl From<&crate::NamedStreamType> for NamedStreamKind {
+    fn from(value: &crate::NamedStreamType) -> Self {
+        match value {
+            crate::NamedStreamType::ReparseData => NamedStreamKind::ReparseData,
+            crate::NamedStreamType::AccessControlList => NamedStreamKind::AccessControlList,
+            crate::NamedStreamType::DosName => NamedStreamKind::DosName,
+            crate::NamedStreamType::ObjectId => NamedStreamKind::ObjectId,
+            crate::NamedStreamType::EncryptedFileSystemInfo => {
+                NamedStreamKind::EncryptedFileSystemInfo
+            }
+            crate::NamedStreamType::ExtendedAttributes => NamedStreamKind::ExtendedAttributes,
+            crate::NamedStreamType::AlternateDataStream { .. } => {
+                NamedStreamKind::AlternateDataStream
+            }
+        }
+    }
+}
+
+impl From<&crate::MetadataChange> for MetadataChange {
+    fn from(value: &crate::MetadataChange) -> Self {
+        use metadata_change::Kind;
+
+        let u32_change = |from, to| OptionalU32Change { from, to };
+        let kind = match value {
+            crate::MetadataChange::Size(change) => {
+                Kind::Size(change_to_proto(change, |from, to| U64Change { from, to }))
+            }
+            crate::MetadataChange::NtfsAttributes(change) => {
+                Kind::NtfsAttributes(change_to_proto(change, u32_change))
+            }
+            crate::MetadataChange::UnixPermissions(change) => {
+                Kind::UnixPermissions(change_to_proto(change, u32_change))
+            }
+            crate::MetadataChange::Nlink(change) => {
+                Kind::Nlink(change_to_proto(change, |from, to| OptionalU64Change {
+                    from,
+                    to,
+                }))
+            }
+            crate::MetadataChange::Uid(change) => Kind::Uid(change_to_proto(change, u32_change)),
+            crate::MetadataChange::Gid(change) => Kind::Gid(change_to_proto(change, u32_change)),
+            crate::MetadataChange::NamedStream(ty, change) => {
+                Kind::NamedStream(NamedStreamChange {
+                    kind: NamedStreamKind::from(ty).into(),
+                    name: match ty {
+                        crate::NamedStreamType::AlternateDataStream { name } => name.clone(),
+                        _ => String::new(),
+                    },
+                    change: Some(OptionalBytesChange {
+                        from: change.from.as_deref().map(<[u8]>::to_vec),
+                        to: change.to.as_deref().map(<[u8]>::to_vec),
+                    }),
+                })
+            }
+            crate::MetadataChange::Unknown(unknown) => Kind::Unknown(UnknownMetadataChange {
+                tag: unknown.tag.clone(),
+                json_value: unknown.value.to_string(),
+            }),
+        };
+
+        MetadataChange { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<MetadataChange> for crate::MetadataChange {
+    type Error = String;
+
+    fn try_from(value: MetadataChange) -> Result<Self, Self::Error> {
+        use metadata_change::Kind;
+
+        Ok(match required(value.kind, "kind")? {
+            Kind::Size(change) => crate::MetadataChange::Size(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::NtfsAttributes(change) => crate::MetadataChange::NtfsAttributes(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::UnixPermissions(change) => {
+                crate::MetadataChange::UnixPermissions(crate::Change {
+                    from: change.from,
+                    to: change.to,
+                })
+            }
+            Kind::Nlink(change) => crate::MetadataChange::Nlink(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::Uid(change) => crate::MetadataChange::Uid(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::Gid(change) => crate::MetadataChange::Gid(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::NamedStream(stream) => {
+                let ty = match NamedStreamKind::try_from(stream.kind)
+                    .map_err(|err| format!("{err}"))?
+                {
+                    NamedStreamKind::Unspecified => {
+                        return Err(String::from("unspecified named stream kind"))
+                    }
+                    NamedStreamKind::ReparseData => crate::NamedStreamType::ReparseData,
+                    NamedStreamKind::AccessControlList => crate::NamedStreamType::AccessControlList,
+                    NamedStreamKind::DosName => crate::NamedStreamType::DosName,
+                    NamedStreamKind::ObjectId => crate::NamedStreamType::ObjectId,
+                    NamedStreamKind::EncryptedFileSystemInfo => {
+                        crate::NamedStreamType::EncryptedFileSystemInfo
+                    }
+                    NamedStreamKind::ExtendedAttributes => {
+                        crate::NamedStreamType::ExtendedAttributes
+                    }
+                    NamedStreamKind::AlternateDataStream => {
+                        crate::NamedStreamType::AlternateDataStream { name: stream.name }
+                    }
+                };
+                let change = required(stream.change, "change")?;
+
+                crate::MetadataChange::NamedStream(
+                    ty,
+                    crate::Change {
+                        from: change.from.map(Arc::from),
+                        to: change.to.map(Arc::from),
+                    },
+                )
+            }
+            Kind::Unknown(unknown) => {
+                crate::MetadataChange::Unknown(crate::UnknownMetadataChange {
+                    tag: unknown.tag,
+                    value: serde_json::from_str(&unknown.json_value)
+                        .map_err(|err| format!("{err}"))?,
+                })
+            }
+        })
+    }
+}
+
+impl From<&crate::MetadataInfo<Timestamp>> for MetadataInfo {
+    fn from(value: &crate::MetadataInfo<Timestamp>) -> Self {
+        MetadataInfo {
+            changes: value.changes.iter().map(Into::into).collect(),
+            inode: Some(maybe_u64_to_proto(&value.inode)),
+            created: Some(maybe_timestamp_to_proto(&value.created)),
+            modified: Some(maybe_timestamp_to_proto(&value.modified)),
+            accessed: Some(maybe_timestamp_to_proto(&value.accessed)),
+            inode_modified: Some(maybe_timestamp_to_proto(&value.inode_modified)),
+        }
+    }
+}
+
+impl TryFrom<MetadataInfo> for crate::MetadataInfo<Timestamp> {
+    type Error = String;
+
+    fn try_from(value: MetadataInfo) -> Result<Self, Self::Error> {
+        Ok(crate::MetadataInfo {
+            changes: value
+                .changes
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            inode: maybe_u64_from_proto(value.inode),
+            created: maybe_timestamp_from_proto(value.created)?,
+            modified: maybe_timestamp_from_proto(value.modified)?,
+            accessed: maybe_timestamp_from_proto(value.accessed)?,
+            inode_modified: maybe_timestamp_from_proto(value.inode_modified)?,
+        })
+    }
+}
+
+impl From<&crate::EntryDiff> for EntryDiff {
+    fn from(value: &crate::EntryDiff) -> Self {
+        use entry_diff::Kind;
+
+        let string_change = |from, to| StringChange { from, to };
+        let kind = match value {
+            crate::EntryDiff::FileChanged { hash_change } => Kind::FileChanged(HashChange {
+                from: hash_change.from.0.to_vec(),
+                to: hash_change.to.0.to_vec(),
+            }),
+            crate::EntryDiff::SymlinkChanged { path_change } => {
+                Kind::SymlinkChanged(change_to_proto(path_change, string_change))
+            }
+            crate::EntryDiff::TypeChange(change) => {
+                Kind::TypeChange(change_to_proto(change, string_change))
+            }
+            crate::EntryDiff::OtherChange => Kind::OtherChange(OtherChange {}),
+        };
+
+        EntryDiff { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<EntryDiff> for crate::EntryDiff {
+    type Error = String;
+
+    fn try_from(value: EntryDiff) -> Result<Self, Self::Error> {
+        use entry_diff::Kind;
+
+        let hash = |bytes: Vec<u8>| {
+            <[u8; 32]>::try_from(bytes)
+                .map(crate::Hash)
+                .map_err(|bytes| format!("expected a 32 byte hash, got {} bytes", bytes.len()))
+        };
+
+        Ok(match required(value.kind, "kind")? {
+            Kind::FileChanged(change) => crate::EntryDiff::FileChanged {
+                hash_change: crate::Change {
+                    from: hash(change.from)?,
+                    to: hash(change.to)?,
+                },
+            },
+            Kind::SymlinkChanged(change) => crate::EntryDiff::SymlinkChanged {
+                path_change: crate::Change {
+                    from: change.from,
+                    to: change.to,
+                },
+            },
+            Kind::TypeChange(change) => crate::EntryDiff::TypeChange(crate::Change {
+                from: change.from,
+                to: change.to,
+            }),
+            Kind::OtherChange(_) => crate::EntryDiff::OtherChange,
+        })
+    }
+}
+
+impl From<&crate::MetaEntryDiff<Timestamp>> for MetaEntryDiff {
+    fn from(value: &crate::MetaEntryDiff<Timestamp>) -> Self {
+        use meta_entry_diff::Kind;
+
+        let kind = match value {
+            crate::MetaEntryDiff::Added(info) => Kind::Added(info.into()),
+            crate::MetaEntryDiff::Deleted(info) => Kind::Deleted(info.into()),
+            crate::MetaEntryDiff::MetaOnlyChange(info) => Kind::MetaOnlyChange(info.into()),
+            crate::MetaEntryDiff::EntryChange(entry, info) => Kind::EntryChange(EntryChange {
+                entry: Some(entry.into()),
+                info: Some(info.into()),
+            }),
+        };
+
+        MetaEntryDiff { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<MetaEntryDiff> for crate::MetaEntryDiff<Timestamp> {
+    type Error = String;
+
+    fn try_from(value: MetaEntryDiff) -> Result<Self, Self::Error> {
+        use meta_entry_diff::Kind;
+
+        Ok(match required(value.kind, "kind")? {
+            Kind::Added(info) => crate::MetaEntryDiff::Added(info.try_into()?),
+            Kind::Deleted(info) => crate::MetaEntryDiff::Deleted(info.try_into()?),
+            Kind::MetaOnlyChange(info) => crate::MetaEntryDiff::MetaOnlyChange(info.try_into()?),
+            Kind::EntryChange(change) => crate::MetaEntryDiff::EntryChange(
+                required(change.entry, "entry")?.try_into()?,
+                required(change.info, "info")?.try_into()?,
+            ),
+        })
+    }
+}
+
+impl From<&crate::Changeset<Timestamp>> for Changeset {
+    fn from(value: &crate::Changeset<Timestamp>) -> Self {
+        Changeset {
+            earliest_timestamp: Some(timestamp_to_proto(&value.earliest_timestamp)),
+            changes: value
+                .changes
+                .iter()
+                .map(|(path, diff)| (path.clone(), diff.into()))
+                .collect(),
+            annotations: value
+                .annotations
+                .iter()
+                .map(|(path, annotation)| (path.clone(), annotation.to_string()))
+                .collect(),
+            risk_scores: value
+                .risk_scores
+                .iter()
+                .map(|(path, score)| (path.clone(), (*score).into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::Changeset<Timestamp>> for Changeset {
+    fn from(value: crate::Changeset<Timestamp>) -> Self {
+        Changeset::from(&value)
+    }
+}
+
+impl TryFrom<Changeset> for crate::Changeset<Timestamp> {
+    type Error = String;
+
+    fn try_from(value: Changeset) -> Result<Self, Self::Error> {
+        Ok(crate::Changeset {
+            schema_version: crate::Changeset::SCHEMA_VERSION,
+            earliest_timestamp: timestamp_from_proto(&required(
+                value.earliest_timestamp,
+                "earliest_timestamp",
+            )?)?,
+            changes: value
+                .changes
+                .into_iter()
+                .map(|(path, diff)| Ok((path, diff.try_into()?)))
+                .collect::<Result<_, String>>()?,
+            annotations: value
+                .annotations
+                .into_iter()
+                .map(|(path, annotation)| {
+                    serde_json::from_str(&annotation)
+                        .map(|annotation| (path, annotation))
+                        .map_err(|err| format!("{err}"))
+                })
+                .collect::<Result<_, _>>()?,
+            risk_scores: value
+                .risk_scores
+                .into_iter()
+                .map(|(path, score)| match u8::try_from(score) {
+                    Ok(score) if score <= 100 => Ok((path, score)),
+                    _ => Err(format!("risk score {score} of `{path}` is above 100")),
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message as _;
+
+    use crate::{
+        tests::{changeset, file_changed, info, ts},
+        Change, EntryDiff, MaybeChange, MetaEntryDiff, MetadataChange, NamedStreamType,
+        UnknownMetadataChange,
+    };
+
+    #[test]
+    fn round_trip() {
+        let stream = |ty| {
+            MetadataChange::NamedStream(
+                ty,
+                Change {
+                    from: None,
+                    to: Some(vec![1, 2].into()),
+                },
+            )
+        };
+        let mut meta = info(vec![
+            MetadataChange::size(1, 2),
+            MetadataChange::ntfs_attributes(None, Some(0x20)),
+            MetadataChange::unix_permissions(Some(0o644), None),
+            MetadataChange::nlink(Some(1), Some(2)),
+            MetadataChange::uid(Some(0), Some(1)),
+            MetadataChange::gid(None, None),
+            stream(NamedStreamType::ReparseData),
+            stream(NamedStreamType::AccessControlList),
+            stream(NamedStreamType::DosName),
+            stream(NamedStreamType::ObjectId),
+            stream(NamedStreamType::EncryptedFileSystemInfo),
+            stream(NamedStreamType::ExtendedAttributes),
+            stream(NamedStreamType::AlternateDataStream {
+                name: "Zone.Identifier".to_string(),
+            }),
+            MetadataChange::Unknown(UnknownMetadataChange {
+                tag: "Future".to_string(),
+                value: serde_json::json!({"x": 1}),
+            }),
+        ]);
+        meta.inode = MaybeChange::Same(Some(3));
+        meta.created = MaybeChange::changed(None, Some(ts(1)));
+        meta.modified = MaybeChange::changed(Some(ts(5)), None);
+        meta.accessed = MaybeChange::Same(Some(ts(7)));
+        let mut cs = changeset(vec![
+            ("/added", MetaEntryDiff::Added(meta)),
+            ("/deleted", MetaEntryDiff::Deleted(info(vec![]))),
+            ("/meta", MetaEntryDiff::MetaOnlyChange(info(vec![]))),
+            (
+                "/file",
+                MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+            ),
+            (
+                "/link",
+                MetaEntryDiff::EntryChange(
+                    EntryDiff::symlink_changed("a".into(), "b".into()),
+                    info(vec![]),
+                ),
+            ),
+            (
+                "/type",
+                MetaEntryDiff::EntryChange(
+                    EntryDiff::type_change("file".into(), "directory".into()),
+                    info(vec![]),
+                ),
+            ),
+            (
+                "/other",
+                MetaEntryDiff::EntryChange(EntryDiff::OtherChange, info(vec![])),
+            ),
+        ]);
+        cs.earliest_timestamp = ts(-5);
+        cs.annotate("/file", serde_json::json!({"verdict": "bad"}));
+        cs.score_risks(crate::default_risk_score);
+        assert!(cs.risk_scores.values().any(|&score| score > 0));
+
+        let encoded = super::Changeset::from(&cs).encode_to_vec();
+        let decoded = super::Changeset::decode(&encoded[..]).unwrap();
+        assert_eq!(crate::Changeset::try_from(decoded).unwrap(), cs);
+    }
+
+    #[test]
+    fn missing_fields_are_rejected() {
+        let mut message = super::Changeset::from(&changeset(vec![(
+            "/a",
+            MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![])),
+        )]));
+        message.earliest_timestamp = None;
+        assert!(crate::Changeset::try_from(message).is_err());
+    }
+
+    #[test]
+    fn risk_scores_above_100_are_rejected() {
+        let mut message =
+            super::Changeset::from(&changeset(vec![("/a", MetaEntryDiff::Added(info(vec![])))]));
+        message.risk_scores.insert("/a".to_string(), 100);
+        assert!(crate::Changeset::try_from(message.clone()).is_ok());
+        message.risk_scores.insert("/a".to_string(), 101);
+        assert_eq!(
+            crate::Changeset::try_from(message).unwrap_err(),
+            "risk score 101 of `/a` is above 100"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Conversion of changesets into rows of an SQLite table.
+//!
+//! Each entry of a changeset is converted into a single `ChangesetRow`. The table layout is
+//! defined by `CREATE_TABLE` and rows are inserted using `INSERT` together with
+//! `ChangesetRow::insert`.
+
+use crate::{Changeset, EntryDiff, MetaEntryDiff, MetaEntryDiffKind, MetadataChange, Timestamp};
+
+/// Creates the table that stores the rows.
+pub const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS changes (
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    old_hash TEXT,
+    new_hash TEXT,
+    size_from INTEGER,
+    size_to INTEGER,
+    modified TEXT
+)";
+
+/// Inserts a single row into the table.
+///
+/// The statement should be prepared once and then used with `ChangesetRow::insert` for each
+/// row.
+pub const INSERT: &str = "INSERT INTO changes
+    (path, kind, old_hash, new_hash, size_from, size_to, modified)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+
+/// A single entry of a changeset, flattened into the columns of a table.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChangesetRow {
+    /// The path of the entry.
+    pub path: String,
+    /// The kind of the change.
+    pub kind: MetaEntryDiffKind,
+    /// The hash of the file before the change, if its contents changed.
+    pub old_hash: Option<crate::Hash>,
+    /// The hash of the file after the change, if its contents changed.
+    pub new_hash: Option<crate::Hash>,
+    /// The size before the change, if it changed.
+    pub size_from: Option<u64>,
+    /// The size after the change, if it changed.
+    pub size_to: Option<u64>,
+    /// The new modification timestamp.
+    pub modified: Option<Timestamp>,
+}
+
+impl ChangesetRow {
+    /// Inserts the row using a statement prepared from `INSERT`.
+    pub fn insert(&self, stmt: &mut rusqlite::Statement) -> rusqlite::Result<usize> {
+        let size = |size: Option<u64>| {
+            size.map(i64::try_from)
+                .transpose()
+                .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+        };
+
+        stmt.execute((
+            &self.path,
+            self.kind.as_str(),
+            self.old_hash.clone().map(String::from),
+            self.new_hash.clone().map(String::from),
+            size(self.size_from)?,
+            size(self.size_to)?,
+            self.modified.as_ref().map(|ts| ts.format_with_precision(9)),
+        ))
+    }
+}
+
+impl Changeset<Timestamp> {
+    /// Converts the entries of the changeset into rows of a table.
+    pub fn to_rows(&self) -> impl Iterator<Item = ChangesetRow> + '_ {
+        self.changes.iter().map(|(path, diff)| {
+            let info = diff.meta_info();
+            let hashes = match diff {
+                MetaEntryDiff::EntryChange(EntryDiff::FileChanged { hash_change }, _) => {
+                    Some(hash_change.clone())
+                }
+                _ => None,
+            };
+            let size = info.changes.iter().find_map(|change| match change {
+                MetadataChange::Size(change) => Some(change),
+                _ => None,
+            });
+
+            ChangesetRow {
+                path: path.clone(),
+                kind: diff.kind(),
+                old_hash: hashes.as_ref().map(|hashes| hashes.from.clone()),
+                new_hash: hashes.map(|hashes| hashes.to),
+                size_from: size.map(|size| size.from),
+                size_to: size.map(|size| size.to),
+                modified: info.modified.new_val().clone(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{changeset, file_changed, info};
+    use crate::{Hash, MetaEntryDiff, MetaEntryDiffKind, MetadataChange};
+
+    #[test]
+    fn file_content_change_row() {
+        let changeset = changeset(vec![(
+            "/a",
+            MetaEntryDiff::EntryChange(file_changed(1, 2), info(vec![MetadataChange::size(1, 2)])),
+        )]);
+
+        let rows: Vec<_> = changeset.to_rows().collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].kind, MetaEntryDiffKind::EntryChange);
+        assert_eq!(rows[0].old_hash, Some(Hash([1; 32])));
+        assert_eq!(rows[0].new_hash, Some(Hash([2; 32])));
+        assert_eq!((rows[0].size_from, rows[0].size_to), (Some(1), Some(2)));
+
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute(super::CREATE_TABLE, ()).unwrap();
+        let mut stmt = db.prepare(super::INSERT).unwrap();
+        rows[0].insert(&mut stmt).unwrap();
+
+        let (kind, new_hash): (String, String) = db
+            .query_row("SELECT kind, new_hash FROM changes", (), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(kind, "entry_change");
+        assert_eq!(new_hash, "02".repeat(32));
+    }
+}